@@ -26,10 +26,94 @@ valid-no-docx-suffix =
 # $filename (String) - Resulting output file name that multiple input values would have due to output pattern rules.
 valid-same-output-filename = Multiple input lines would have same output filename "{ $filename }"!
 
+# $token (String) - Token mapped by the profile, but not found in the template.
+profile-unknown-token = Profile maps token { $token } which is not in the template.
+# $token (String) - Token of the template having no column nor constant value in the profile.
+profile-unmapped-token = Token { $token } of the template is not mapped by the profile.
+# $column (String) - Column required by the profile, but missing in the input data.
+profile-missing-column = Input data has no column "{ $column }" required by the mapping profile!
+
+# $line (String) - Number of the line in constants file where the problem is.
+constants-invalid-line = Constants file line { $line } is not in "token = value" form!
+
+# $filename (String) - Lock file of a store held by other running generation.
+store-locked = Store is used by another generation, try again later (lock file "{ $filename }").
+
+# $number (String) - Number of the registered certificate.
+# $date (String) - Date and time the certificate was generated.
+# $filename (String) - File the certificate was generated into.
+verify-genuine =
+    Certificate is genuine.
+    Number: { $number }, issued: { $date } ({ $filename }).
+verify-altered =
+    Certificate was ALTERED after it was issued!
+    It claims number { $number }, issued: { $date } ({ $filename }).
+verify-recipient-mismatch =
+    Certificate number { $number } was issued to a different recipient!
+    Issued: { $date } ({ $filename }).
+verify-unknown = Certificate was not found in the registry!
+verify-revoked =
+    Certificate number { $number } was REVOKED: { $reason }
+    Issued: { $date } ({ $filename }).
+verify-superseded =
+    Certificate number { $number } was replaced by a corrected version!
+    Issued: { $date } ({ $filename }).
+registry-no-active-certificate = No active certificate "{ $id }" found in the registry!
+registry-archived-certificate = Certificate "{ $filename }" is stored in an archive and cannot be reissued!
+duplicate-row = Row { $row } ({ $recipient }) repeats row { $other }.
+duplicate-row-similar = Row { $row } ({ $recipient }) looks like row { $other } ({ $other_recipient }).
+duplicate-issued = Row { $row } ({ $recipient }) already got certificate { $number } on { $date }.
+duplicate-issued-similar = Row { $row } ({ $recipient }) looks like { $other_recipient }, who got certificate { $number } on { $date }.
+package-duplicate-part = Part { $part } is written into the document twice!
+batch-row-failed = Row { $row }: { $error }
+batch-not-recorded = Documents were generated, but they could not be recorded into the registry: { $details }
+report-column-row = Row
+report-column-status = Status
+report-column-file = File
+report-column-reason = Reason
+report-status-generated = generated
+report-status-failed = failed
+report-status-resumed = generated before
+report-status-overwritten = overwritten
+report-status-unchanged = unchanged
+report-status-skipped = skipped (file exists)
+manifest-missing = Batch manifest is needed to resume the batch!
+manifest-unsupported = Merged and archived batches cannot be resumed, they are generated without the batch manifest!
+merge-unknown-group-token = Token { $token } the documents are grouped by is not filled in the batch!
+merge-no-body = Document of the template has no body to be merged!
+# $part (String) - name of the part, e.g. the header
+merge-part-differs = Part { $part } of the merged document would differ between the documents merged together!
+archive-missing-pattern = Archive pattern is needed to write the batch into archives!
+# $filename (String) - path of the document within the archive
+archive-same-entry = Multiple input lines would have same file "{ $filename }" in archive "{ $archive }"!
+archive-column-number = Number
+report-column-pdf = PDF
+report-status-conversion-failed = generated, PDF failed
+convert-failed = Converting { $filename } to PDF failed! { $details }
+# $program (String) - path of the LibreOffice executable
+convert-no-libreoffice = LibreOffice ({ $program }) could not be started: { $details }
+# $details (String) - error reported by the system
+error-io = File operation failed: { $details }
+error-zip = DOCX (zip) file is damaged: { $details }
+error-csv = CSV data cannot be read: { $details }
+error-json = JSON file cannot be read: { $details }
+# $part (String) - name of the part within the DOCX file
+error-missing-part = DOCX file has no part { $part }!
+error-invalid-part = Part { $part } of the DOCX file is not a valid text!
+# $filename (String) - path of the input data file
+error-unsupported-data = File { $filename } is not supported input data, use CSV or Excel (.xlsx) file!
+error-xlsx = Excel file { $filename } cannot be read: { $details }
+
 #
 ui-docx-app-title = docx template filler
 ui-docx-load-failed = Failed to load docx template!
 ui-docx-generated = Created docx files succesfully.
+ui-docx-generated-with-failures =
+    Created { $generated } docx files, { $failed } rows failed.
+    Report of the failed rows was saved to { $filename }
+ui-docx-streamed = Created docx files from { $rows } rows of the data file.
+ui-docx-merged = Merged { $rows } rows into { $files } docx files.
+ui-docx-archived = Created { $rows } docx files in { $files } zip archives.
 ui-docx-success =  Success
 ui-docx-failure = Oops...
 ui-docx-no-template = No template file opened yet!
@@ -45,6 +129,7 @@ ui-template-default-folder-fail = Failed to set working folder!
 ui-data-label = Currently loaded input data:
 ui-data-dialog = Open File
 ui-data-button = Load new Data
+ui-data-load-failed = Failed to load input data!
 ui-data-default-folder-fail = Failed to set working folder!
 #
 ui-tokens-label = Tokens (placeholders) found in file:
@@ -57,6 +142,24 @@ ui-values-label = Values to be filled over tokens (one line per docx to create):
 #
 ui-output-label = Output files name pattern:
 ui-output-button = Generate DOCX files
+ui-output-profile-button = Save mapping profile
+ui-profile-saved = Mapping profile saved alongside the template.
+ui-profile-warnings = Mapping profile does not fully match the template
 ui-options-sep-label = Value separator:
+#
+ui-verify-title = Certificate verification
+ui-verify-usage =
+    Usage: --verify <file.docx>
+    or: --verify <number> <recipient name>
+ui-registry-title = Certificate registry
+ui-revoke-usage = Usage: --revoke <number or SHA-256> <reason>
+ui-revoked = Certificate { $number } was revoked.
+ui-reissue-usage = Usage: --reissue <number or SHA-256> <token>=<corrected value> ...
+ui-reissued = Corrected certificate { $number } was created: { $filename }
+ui-duplicates-title = Duplicate recipients
+ui-duplicates-question =
+    Duplicate recipients were found:
+    { $duplicates }
+    Leave the duplicate rows out? (Yes - leave out, No - generate all, Cancel - stop)
 
 lang-not-found = Cannot switch to requested language!
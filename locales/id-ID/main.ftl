@@ -26,10 +26,94 @@ valid-no-docx-suffix =
 # $filename (String) - Nama dari beberapa File output harus mengikuti peraturan penamaan!.
 valid-same-output-filename = Lebih dari satu input line harus memiliki nama file yang sama "{ $filename }"!
 
+# $token (String) - Token yang dipetakan profil, tetapi tidak ditemukan pada template.
+profile-unknown-token = Profil memetakan token { $token } yang tidak ada pada template.
+# $token (String) - Token pada template yang tidak memiliki kolom maupun nilai konstan pada profil.
+profile-unmapped-token = Token { $token } pada template tidak dipetakan oleh profil.
+# $column (String) - Kolom yang dibutuhkan profil, tetapi tidak ada pada data input.
+profile-missing-column = Data input tidak memiliki kolom "{ $column }" yang dibutuhkan profil pemetaan!
+
+# $line (String) - Nomor baris pada file konstanta yang bermasalah.
+constants-invalid-line = Baris { $line } pada file konstanta tidak berformat "token = nilai"!
+
+# $filename (String) - File kunci penyimpanan yang dipegang proses pembuatan lain.
+store-locked = Penyimpanan sedang digunakan proses lain, coba lagi nanti (file kunci "{ $filename }").
+
+# $number (String) - Nomor piagam yang terdaftar.
+# $date (String) - Tanggal dan waktu piagam dibuat.
+# $filename (String) - File hasil pembuatan piagam.
+verify-genuine =
+    Piagam asli.
+    Nomor: { $number }, diterbitkan: { $date } ({ $filename }).
+verify-altered =
+    Piagam TELAH DIUBAH setelah diterbitkan!
+    Piagam mengaku bernomor { $number }, diterbitkan: { $date } ({ $filename }).
+verify-recipient-mismatch =
+    Piagam nomor { $number } diterbitkan untuk penerima yang berbeda!
+    Diterbitkan: { $date } ({ $filename }).
+verify-unknown = Piagam tidak ditemukan pada daftar piagam terbit!
+verify-revoked =
+    Piagam nomor { $number } telah DICABUT: { $reason }
+    Diterbitkan: { $date } ({ $filename }).
+verify-superseded =
+    Piagam nomor { $number } telah digantikan oleh versi perbaikan!
+    Diterbitkan: { $date } ({ $filename }).
+registry-no-active-certificate = Piagam aktif "{ $id }" tidak ditemukan pada daftar piagam terbit!
+registry-archived-certificate = Piagam "{ $filename }" tersimpan dalam arsip dan tidak dapat diterbitkan ulang!
+duplicate-row = Baris { $row } ({ $recipient }) mengulang baris { $other }.
+duplicate-row-similar = Baris { $row } ({ $recipient }) mirip dengan baris { $other } ({ $other_recipient }).
+duplicate-issued = Baris { $row } ({ $recipient }) sudah menerima piagam { $number } pada { $date }.
+duplicate-issued-similar = Baris { $row } ({ $recipient }) mirip dengan { $other_recipient }, penerima piagam { $number } pada { $date }.
+package-duplicate-part = Bagian { $part } tertulis dua kali pada dokumen!
+batch-row-failed = Baris { $row }: { $error }
+batch-not-recorded = Dokumen telah dibuat, tetapi gagal dicatat pada daftar piagam terbit: { $details }
+report-column-row = Baris
+report-column-status = Status
+report-column-file = File
+report-column-reason = Alasan
+report-status-generated = berhasil
+report-status-failed = gagal
+report-status-resumed = dibuat sebelumnya
+report-status-overwritten = ditimpa
+report-status-unchanged = tidak berubah
+report-status-skipped = dilewati (file sudah ada)
+manifest-missing = Manifest batch diperlukan untuk melanjutkan batch!
+manifest-unsupported = Batch gabungan dan arsip tidak dapat dilanjutkan, keduanya dibuat tanpa manifest batch!
+merge-unknown-group-token = Token { $token } untuk mengelompokkan dokumen tidak diisi dalam batch!
+merge-no-body = Dokumen template tidak memiliki body untuk digabungkan!
+# $part (String) - name of the part, e.g. the header
+merge-part-differs = Bagian { $part } dokumen gabungan akan berbeda antar dokumen yang digabungkan!
+archive-missing-pattern = Pola nama arsip diperlukan untuk menulis batch ke dalam arsip!
+# $filename (String) - path dokumen di dalam arsip
+archive-same-entry = Lebih dari satu input line memiliki file yang sama "{ $filename }" di arsip "{ $archive }"!
+archive-column-number = Nomor
+report-column-pdf = PDF
+report-status-conversion-failed = berhasil, PDF gagal
+convert-failed = Konversi { $filename } ke PDF gagal! { $details }
+# $program (String) - path program LibreOffice
+convert-no-libreoffice = LibreOffice ({ $program }) tidak dapat dijalankan: { $details }
+# $details (String) - error yang dilaporkan sistem
+error-io = Operasi file gagal: { $details }
+error-zip = File DOCX (zip) rusak: { $details }
+error-csv = Data CSV tidak dapat dibaca: { $details }
+error-json = File JSON tidak dapat dibaca: { $details }
+# $part (String) - nama bagian di dalam file DOCX
+error-missing-part = File DOCX tidak memiliki bagian { $part }!
+error-invalid-part = Bagian { $part } dari file DOCX bukan teks yang valid!
+# $filename (String) - path file data input
+error-unsupported-data = File { $filename } bukan data input yang didukung, gunakan file CSV atau Excel (.xlsx)!
+error-xlsx = File Excel { $filename } tidak dapat dibaca: { $details }
+
 #
 ui-docx-app-title = Piagam Dispendik Filler
 ui-docx-load-failed = Gagal Memuat file Docx!
 ui-docx-generated = Berhasil Mengenerate / Membuat File Docx dari inputan.
+ui-docx-generated-with-failures =
+    Berhasil membuat { $generated } file docx, { $failed } baris gagal.
+    Laporan baris yang gagal disimpan ke { $filename }
+ui-docx-streamed = Berhasil membuat file docx dari { $rows } baris file data.
+ui-docx-merged = Berhasil menggabungkan { $rows } baris ke dalam { $files } file docx.
+ui-docx-archived = Berhasil membuat { $rows } file docx dalam { $files } arsip zip.
 ui-docx-success =  Sukses!
 ui-docx-failure = Waduh...
 ui-docx-no-template = Anda belum memberikan input file template yang akan di isi!
@@ -45,6 +129,7 @@ ui-template-default-folder-fail = Terjadi Kesalahan saat menyetel folder kerja!
 ui-data-dialog = Buka File Data Input
 ui-data-label = Data saat ini yang termuat:
 ui-data-button = Muat Data Baru
+ui-data-load-failed = Gagal memuat data input!
 ui-data-default-folder-fail = Terjadi Kesalahan saat menyetel folder kerja!
 #
 ui-tokens-label = Tokens (placeholders) ditemukan pada file:
@@ -57,6 +142,24 @@ ui-values-label = Isi Setiap Value sesuai dengan Token (Satu Line per Halaman):
 #
 ui-output-label = Pola Nama File Output:
 ui-output-button = Hasilkan Docx File
+ui-output-profile-button = Simpan Profil Pemetaan
+ui-profile-saved = Profil pemetaan disimpan bersama file template.
+ui-profile-warnings = Profil pemetaan tidak sepenuhnya cocok dengan template
 ui-options-sep-label = Karakter Pemisah:
+#
+ui-verify-title = Verifikasi Piagam
+ui-verify-usage =
+    Penggunaan: --verify <file.docx>
+    atau: --verify <nomor> <nama penerima>
+ui-registry-title = Daftar Piagam Terbit
+ui-revoke-usage = Penggunaan: --revoke <nomor atau SHA-256> <alasan>
+ui-revoked = Piagam { $number } telah dicabut.
+ui-reissue-usage = Penggunaan: --reissue <nomor atau SHA-256> <token>=<nilai perbaikan> ...
+ui-reissued = Piagam perbaikan { $number } telah dibuat: { $filename }
+ui-duplicates-title = Penerima Ganda
+ui-duplicates-question =
+    Ditemukan penerima ganda:
+    { $duplicates }
+    Lewati baris ganda? (Yes - lewati, No - buat semua, Cancel - batalkan)
 
 lang-not-found = Tidak dapat mengubah bahasa pada bahasa yang diminta!
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::RwLock, iter::FromIterator};
+use std::{collections::HashMap, sync::PoisonError, sync::RwLock, iter::FromIterator};
 
 use unic_langid::{langid, LanguageIdentifier};
 
@@ -66,7 +66,10 @@ static_loader! {
 
 /// Sets the target language to be used for all the further tr()/tr_with_args() calls for text translations.
 pub fn set_current_lang(lang: SupportedLanguage) {
-    let mut current = CURRENT_LANGUAGE.write().unwrap();
+    // the language is replaced as a whole, so it stays valid even when the lock was poisoned
+    let mut current = CURRENT_LANGUAGE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
     *current = lang;
 }
 
@@ -105,7 +108,10 @@ fn translate(text_id: &str, args: Option<&ArgsMap>) -> String {
 
 // Returns currently set language.
 fn current_lang() -> SupportedLanguage {
-    CURRENT_LANGUAGE.read().unwrap().clone()
+    CURRENT_LANGUAGE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
 }
 
 /// Creates args map for the string translation.
@@ -16,5 +16,11 @@ mod ui;
 
 fn main() {
     lang::set_current_lang(SupportedLanguage::EnglishUs);
-    ui::init_app();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--verify") => ui::verify_certificate(&args[1..]),
+        Some("--revoke") => ui::revoke_certificate(&args[1..]),
+        Some("--reissue") => ui::reissue_certificate(&args[1..]),
+        _ => ui::init_app(),
+    }
 }
@@ -0,0 +1,153 @@
+use super::{DocxResult, TokenPack, TokenPackArg, ValuePack, ValuePackArg};
+use crate::lang;
+use std::io::{self, Seek, Write};
+use std::path::PathBuf;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+/// Name of the index of the documents stored in each archive.
+pub const INDEX_ENTRY: &str = "index.csv";
+
+/// Writer of a ZIP archive of generated documents, along with their index.
+pub(super) struct ArchiveWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    index: csv::Writer<Vec<u8>>,
+    last_modified: DateTime,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    /// Starts the archive, index columns are the row, file, certificate number and the tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the archive is written into
+    /// * `tokens` - tokens listed in the index
+    /// * `last_modified` - timestamp of all the entries, so the same documents make the same archive
+    pub fn new(writer: W, tokens: TokenPackArg, last_modified: DateTime) -> DocxResult<Self> {
+        let mut index = csv::Writer::from_writer(Vec::new());
+        let mut header = vec![
+            lang::tr("report-column-row"),
+            lang::tr("report-column-file"),
+            lang::tr("archive-column-number"),
+        ];
+        header.extend(tokens.iter().cloned());
+        index.write_record(&header)?;
+        Ok(Self {
+            zip: ZipWriter::new(writer),
+            index,
+            last_modified,
+        })
+    }
+
+    /// Stores the document (as it is, DOCX is compressed already) and adds it into the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - path of the document within the archive
+    /// * `row` - source data row number of the document
+    /// * `number` - certificate number of the document
+    /// * `values` - values of the tokens
+    /// * `content` - the DOCX file
+    pub fn add(
+        &mut self,
+        name: &str,
+        row: usize,
+        number: Option<&str>,
+        values: ValuePackArg,
+        content: &[u8],
+    ) -> DocxResult<()> {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(self.last_modified);
+        self.zip.start_file(name, options)?;
+        self.zip.write_all(content)?;
+
+        let mut record = vec![
+            row.to_string(),
+            name.to_owned(),
+            number.unwrap_or_default().to_owned(),
+        ];
+        record.extend(values.iter().cloned());
+        self.index.write_record(&record)?;
+        Ok(())
+    }
+
+    /// Writes the index, finishes the archive and returns the underlying writer.
+    pub fn finish(mut self) -> DocxResult<W> {
+        let index = self
+            .index
+            .into_inner()
+            .map_err(|err| io::Error::new(err.error().kind(), err.error().to_string()))?;
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(self.last_modified);
+        self.zip.start_file(INDEX_ENTRY, options)?;
+        self.zip.write_all(&index)?;
+        Ok(self.zip.finish()?)
+    }
+}
+
+/// Archive of a batch, with the documents planned into it.
+pub(super) struct PlannedArchive {
+    pub path: PathBuf,
+    pub entries: Vec<PlannedEntry>,
+}
+
+/// Document of a line of the batch, planned into an archive.
+pub(super) struct PlannedEntry {
+    pub line: usize,
+    /// path of the document within the archive.
+    pub name: String,
+    pub tokens: TokenPack,
+    pub values: ValuePack,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::strings;
+    use std::io::{Cursor, Read};
+
+    fn archive(documents: &[(&str, &str)]) -> Vec<u8> {
+        let tokens = strings(&["{{nama}}"]);
+        let mut writer =
+            ArchiveWriter::new(Cursor::new(Vec::new()), &tokens, DateTime::default()).unwrap();
+        for (row, (name, content)) in documents.iter().enumerate() {
+            let values = strings(&[name.trim_end_matches(".docx")]);
+            let number = format!("{:03}", row + 1);
+            writer
+                .add(name, row + 2, Some(&number), &values, content.as_bytes())
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn documents_are_stored_along_with_index() {
+        let content = archive(&[("Budi.docx", "PK budi"), ("6A/Ani.docx", "PK ani")]);
+        let mut zip = zip::ZipArchive::new(Cursor::new(content)).unwrap();
+
+        let names: Vec<String> = (0..zip.len())
+            .map(|i| zip.by_index(i).unwrap().name().to_owned())
+            .collect();
+        assert_eq!(names, ["Budi.docx", "6A/Ani.docx", INDEX_ENTRY]);
+        let mut entry = zip.by_name("6A/Ani.docx").unwrap();
+        assert_eq!(entry.compression(), CompressionMethod::Stored);
+        let mut document = String::new();
+        entry.read_to_string(&mut document).unwrap();
+        assert_eq!(document, "PK ani");
+        drop(entry);
+
+        let mut index = String::new();
+        let mut entry = zip.by_name(INDEX_ENTRY).unwrap();
+        entry.read_to_string(&mut index).unwrap();
+        let rows: Vec<&str> = index.lines().skip(1).collect();
+        assert_eq!(rows, ["2,Budi.docx,001,Budi", "3,6A/Ani.docx,002,6A/Ani"]);
+    }
+
+    #[test]
+    fn same_documents_make_the_same_archive() {
+        let documents = [("Budi.docx", "PK budi"), ("Ani.docx", "PK ani")];
+        assert_eq!(archive(&documents), archive(&documents));
+    }
+}
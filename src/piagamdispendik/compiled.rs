@@ -0,0 +1,116 @@
+use super::{TokenPackArg, ValuePackArg};
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+
+lazy_static! {
+    /// Token (placeholder) slot in the XML of a part, e.g. `{{nama}}`.
+    static ref TOKEN_SLOT: Regex = Regex::new(r"\{\{.*?\}\}").expect("valid token regex");
+}
+
+/// Piece of the compiled part - either literal XML or slot of a token.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(Range<usize>),
+    Slot(Range<usize>),
+}
+
+/// Part of the DOCX template compiled into a list of literal XML and token slots,
+/// so each document is rendered in a single pass over the part.
+#[derive(Debug, Clone, Default)]
+pub struct CompiledPart {
+    content: String,
+    segments: Vec<Segment>,
+}
+
+impl CompiledPart {
+    /// Splits the part content into literal XML and token slots.
+    pub fn compile(content: String) -> Self {
+        let mut segments: Vec<Segment> = Default::default();
+        let mut literal_start = 0;
+        for slot in TOKEN_SLOT.find_iter(&content) {
+            if slot.start() > literal_start {
+                segments.push(Segment::Literal(literal_start..slot.start()));
+            }
+            segments.push(Segment::Slot(slot.range()));
+            literal_start = slot.end();
+        }
+        if literal_start < content.len() {
+            segments.push(Segment::Literal(literal_start..content.len()));
+        }
+        Self { content, segments }
+    }
+
+    /// Returns the original (unfilled) content of the part.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Returns the tokens of all the slots, in order of their appearance (including repeated ones).
+    pub fn slots(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Slot(range) => Some(&self.content[range.clone()]),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Renders the part, slots of the given tokens are filled with their values,
+    /// slots of other tokens are kept as they are. Values are inserted as they are,
+    /// tokens contained in the values are never filled.
+    pub fn render(&self, tokens: TokenPackArg, values: ValuePackArg) -> String {
+        let filled: HashMap<&str, &str> = tokens
+            .iter()
+            .map(|token| token.as_str())
+            .zip(values.iter().map(|value| value.as_str()))
+            .collect();
+        let mut output = String::with_capacity(self.content.len());
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Literal(range) => output.push_str(&self.content[range.clone()]),
+                Segment::Slot(range) => {
+                    let slot = &self.content[range.clone()];
+                    output.push_str(filled.get(slot).copied().unwrap_or(slot));
+                }
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::strings;
+
+    #[test]
+    fn compile_splits_literals_and_slots() {
+        let part = CompiledPart::compile("<w:t>{{nama}}, {{kelas}}{{nama}}</w:t>".to_owned());
+        assert_eq!(
+            part.slots().collect::<Vec<&str>>(),
+            vec!["{{nama}}", "{{kelas}}", "{{nama}}"]
+        );
+        assert_eq!(part.segments.len(), 6);
+        assert_eq!(part.content(), "<w:t>{{nama}}, {{kelas}}{{nama}}</w:t>");
+    }
+
+    #[test]
+    fn render_fills_known_tokens_and_keeps_others() {
+        let part = CompiledPart::compile("{{nama}} - {{kelas}} - {{sekolah}}".to_owned());
+        let rendered = part.render(
+            &strings(&["{{nama}}", "{{kelas}}"]),
+            &strings(&["Budi {{kelas}}", "6"]),
+        );
+        assert_eq!(rendered, "Budi {{kelas}} - 6 - {{sekolah}}");
+    }
+
+    #[test]
+    fn render_of_part_without_slots_keeps_content() {
+        let part = CompiledPart::compile("<w:p/>".to_owned());
+        assert_eq!(part.slots().count(), 0);
+        assert_eq!(
+            part.render(&strings(&["{{nama}}"]), &strings(&["Budi"])),
+            "<w:p/>"
+        );
+        assert_eq!(CompiledPart::compile(String::new()).render(&[], &[]), "");
+    }
+}
@@ -0,0 +1,203 @@
+use super::{DocxError, DocxResult, TokenPack, TokenPackArg, ValuePack, ValuePackArg};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name suffix of a constants file stored alongside its DOCX template.
+pub const CONSTANTS_EXTENSION: &str = "constants.txt";
+
+/// Batch-level constant values - tokens filled with the same value in every generated document
+/// (issue date, event name, head of office...), so they do not have to be repeated in every data row.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchConstants {
+    entries: Vec<(String, String)>,
+}
+
+impl BatchConstants {
+    /// Loads constants from a small text file, one `token = value` per line.
+    /// Token may be written with or without the curly braces, lines starting with `#` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::InvalidConstantsLine on a line without `=` separator.
+    pub fn load<P: AsRef<Path>>(path: P) -> DocxResult<Self> {
+        let content = fs::read_to_string(path.as_ref())?;
+        let mut constants = Self::default();
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((token, value)) if !token.trim().is_empty() => {
+                    constants.insert(token, value.trim());
+                }
+                _ => {
+                    return Err(DocxError::InvalidConstantsLine { line: idx + 1 });
+                }
+            }
+        }
+        Ok(constants)
+    }
+
+    /// Loads the constants file stored alongside the template, if there is any.
+    pub fn load_for_template<P: AsRef<Path>>(template: P) -> DocxResult<Option<Self>> {
+        let path = constants_path(template);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::load(path).map(Some)
+    }
+
+    /// Sets value of the token, replacing the previous one if already present.
+    pub fn insert(&mut self, token: &str, value: &str) {
+        let token = normalize_token(token);
+        match self.entries.iter_mut().find(|(t, _)| *t == token) {
+            Some(entry) => entry.1 = value.to_owned(),
+            None => self.entries.push((token, value.to_owned())),
+        }
+    }
+
+    /// Adds all the entries of other constants, overriding values of the same tokens.
+    pub fn extend(&mut self, other: &BatchConstants) {
+        for (token, value) in other.iter() {
+            self.insert(token, value);
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains(&self, token: &str) -> bool {
+        self.get(token).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(t, v)| (t.as_str(), v.as_str()))
+    }
+
+    /// Returns tokens of the pack that are not covered by constants - the ones expected in each data row.
+    pub fn row_tokens(&self, tokens: TokenPackArg) -> TokenPack {
+        tokens
+            .iter()
+            .filter(|token| !self.contains(token))
+            .cloned()
+            .collect()
+    }
+
+    /// Combines the values of a single data row with the constants into full set of tokens and values.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced (including the ones covered by constants)
+    /// * `row_values` - values of a data row, one per each token returned by [`BatchConstants::row_tokens`]
+    pub fn merge(&self, tokens: TokenPackArg, row_values: ValuePackArg) -> (TokenPack, ValuePack) {
+        let mut merged_tokens = self.row_tokens(tokens);
+        let mut merged_values: ValuePack = row_values.to_vec();
+        for (token, value) in self.iter() {
+            merged_tokens.push(token.to_owned());
+            merged_values.push(value.to_owned());
+        }
+        (merged_tokens, merged_values)
+    }
+}
+
+/// Returns path of the constants file stored alongside the template (`piagam.docx` -> `piagam.constants.txt`).
+pub fn constants_path<P: AsRef<Path>>(template: P) -> PathBuf {
+    template.as_ref().with_extension(CONSTANTS_EXTENSION)
+}
+
+/// Wraps bare token name into curly braces (`tanggal` -> `{{tanggal}}`).
+fn normalize_token(token: &str) -> String {
+    let token = token.trim();
+    if token.starts_with("{{") && token.ends_with("}}") {
+        token.to_owned()
+    } else {
+        format!("{{{{{}}}}}", token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{strings, TempDir};
+
+    #[test]
+    fn load_reads_tokens_with_or_without_braces() {
+        let dir = TempDir::new("constants");
+        let path = dir.join("piagam.constants.txt");
+        fs::write(
+            &path,
+            "# acara\ntanggal = 1 Mei 2026\n\n{{kepala}}= Drs. Budi = M.Pd\n",
+        )
+        .unwrap();
+
+        let constants = BatchConstants::load(&path).unwrap();
+
+        assert_eq!(
+            constants.iter().collect::<Vec<_>>(),
+            vec![
+                ("{{tanggal}}", "1 Mei 2026"),
+                ("{{kepala}}", "Drs. Budi = M.Pd")
+            ]
+        );
+    }
+
+    #[test]
+    fn load_fails_on_line_without_separator() {
+        let dir = TempDir::new("constants-invalid");
+        let path = dir.join("piagam.constants.txt");
+        fs::write(&path, "tanggal = 1 Mei 2026\nkepala\n").unwrap();
+
+        let err = BatchConstants::load(&path).unwrap_err();
+
+        assert!(matches!(err, DocxError::InvalidConstantsLine { line: 2 }));
+    }
+
+    #[test]
+    fn load_for_template_uses_constants_file_of_the_template() {
+        let dir = TempDir::new("constants-template");
+        let template = dir.join("piagam.docx");
+        assert_eq!(BatchConstants::load_for_template(&template).unwrap(), None);
+
+        fs::write(dir.join("piagam.constants.txt"), "tanggal = 1 Mei 2026").unwrap();
+        fs::write(dir.join("catatan.txt"), "tanggal = 2 Mei 2026").unwrap();
+
+        let constants = BatchConstants::load_for_template(&template)
+            .unwrap()
+            .unwrap();
+        assert_eq!(constants.get("{{tanggal}}"), Some("1 Mei 2026"));
+    }
+
+    #[test]
+    fn merge_appends_constants_to_row_values() {
+        let mut constants = BatchConstants::default();
+        constants.insert("tanggal", "1 Mei 2026");
+        constants.insert("{{tanggal}}", "2 Mei 2026");
+        let tokens = strings(&["{{nama}}", "{{tanggal}}", "{{sekolah}}"]);
+
+        assert_eq!(constants.len(), 1);
+        assert_eq!(
+            constants.row_tokens(&tokens),
+            strings(&["{{nama}}", "{{sekolah}}"])
+        );
+        assert_eq!(
+            constants.merge(&tokens, &strings(&["Budi", "SDN 1"])),
+            (
+                strings(&["{{nama}}", "{{sekolah}}", "{{tanggal}}"]),
+                strings(&["Budi", "SDN 1", "2 Mei 2026"])
+            )
+        );
+    }
+}
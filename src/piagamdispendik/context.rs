@@ -0,0 +1,239 @@
+use super::{TokenPack, ValuePack};
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+/// Prefix of the tokens reserved for values filled in automatically (not taken from data).
+pub const RESERVED_PREFIX: &str = "{{_";
+
+/// Running number of the document in the batch (see [`SequenceOptions`]).
+pub const TOKEN_SEQUENCE: &str = "{{_urut}}";
+
+/// Total count of the documents in the batch.
+pub const TOKEN_TOTAL: &str = "{{_jumlah}}";
+
+/// Number of the row in the source data the document is generated from.
+pub const TOKEN_SOURCE_ROW: &str = "{{_baris}}";
+
+/// Date and time the batch generation started.
+pub const TOKEN_TIMESTAMP: &str = "{{_waktu}}";
+
+/// Certificate number assigned to the document (see [`super::numbering`]),
+/// empty when the batch is not numbered.
+pub const TOKEN_NUMBER: &str = "{{_nomor}}";
+
+/// Revision of a reissued certificate (empty for the originally issued one).
+pub const TOKEN_REVISION: &str = "{{_revisi}}";
+
+/// Whether the token is reserved for built-in values, rather than being filled from data.
+pub fn is_reserved(token: &str) -> bool {
+    token.starts_with(RESERVED_PREFIX)
+}
+
+/// Formatting of the running number of the documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceOptions {
+    /// number of the first document of the batch.
+    pub start: usize,
+    /// minimal count of digits, padded with zeros (`0` - no padding).
+    pub padding: usize,
+}
+
+impl Default for SequenceOptions {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            padding: 0,
+        }
+    }
+}
+
+/// Built-in values of a single batch - filled into the reserved tokens of each document.
+#[derive(Debug, Clone)]
+pub struct RowContext {
+    sequence: SequenceOptions,
+    total: Option<usize>,
+    source_rows: Vec<usize>,
+    timestamp: String,
+    numbers: Vec<String>,
+    /// index of the line the numbers and the source rows start at.
+    offset: usize,
+}
+
+impl RowContext {
+    /// Creates context of a batch, the generation timestamp is taken at this moment.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence` - formatting of the running number
+    /// * `total` - count of the documents in the batch
+    /// * `source_rows` - source data row number of each document (line number is used when missing)
+    pub fn new(sequence: &SequenceOptions, total: usize, source_rows: &[usize]) -> Self {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        Self {
+            sequence: sequence.clone(),
+            total: Some(total),
+            source_rows: source_rows.to_vec(),
+            timestamp: String::new(),
+            numbers: Vec::new(),
+            offset: 0,
+        }
+        .with_timestamp(now)
+    }
+
+    /// Sets the generation time of the batch (e.g. fixed one of deterministic output).
+    pub fn with_timestamp(mut self, time: OffsetDateTime) -> Self {
+        let format = format_description!("[day]-[month]-[year] [hour].[minute]");
+        self.timestamp = time.format(&format).unwrap_or_default();
+        self
+    }
+
+    /// Marks the total count of the documents unknown (streamed batch), the total token is left empty.
+    pub fn without_total(mut self) -> Self {
+        self.total = None;
+        self
+    }
+
+    /// Sets certificate numbers assigned to the documents of the batch (in order of the lines).
+    pub fn with_numbers(mut self, numbers: Vec<String>) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Returns context of a part of the batch starting at the `offset` line,
+    /// with certificate numbers and source data row numbers of its lines.
+    pub fn part(&self, offset: usize, numbers: Vec<String>, source_rows: Vec<usize>) -> Self {
+        Self {
+            numbers,
+            source_rows,
+            offset,
+            ..self.clone()
+        }
+    }
+
+    /// Returns certificate number assigned to the idx-th document of the batch.
+    pub fn number(&self, idx: usize) -> Option<&str> {
+        idx.checked_sub(self.offset)
+            .and_then(|idx| self.numbers.get(idx))
+            .map(|number| number.as_str())
+    }
+
+    /// Returns source data row number of the idx-th document of the batch (line number when unknown).
+    pub fn source_row(&self, idx: usize) -> usize {
+        idx.checked_sub(self.offset)
+            .and_then(|idx| self.source_rows.get(idx))
+            .copied()
+            .unwrap_or(idx + 1)
+    }
+
+    /// Returns the formatted running number of the idx-th document of the batch.
+    pub fn sequence_number(&self, idx: usize) -> String {
+        format!("{:0width$}", self.sequence.start + idx, width = self.sequence.padding)
+    }
+
+    /// Appends the reserved tokens and their values for the idx-th document of the batch.
+    /// Tokens already present in the pack are kept untouched.
+    pub fn extend(&self, idx: usize, tokens: &mut TokenPack, values: &mut ValuePack) {
+        let source_row = self.source_row(idx);
+        let number = self.number(idx).unwrap_or_default();
+        let context = vec![
+            (TOKEN_SEQUENCE, self.sequence_number(idx)),
+            (
+                TOKEN_TOTAL,
+                self.total.map(|t| t.to_string()).unwrap_or_default(),
+            ),
+            (TOKEN_SOURCE_ROW, source_row.to_string()),
+            (TOKEN_TIMESTAMP, self.timestamp.clone()),
+            (TOKEN_REVISION, String::new()),
+            (TOKEN_NUMBER, number.to_owned()),
+        ];
+        for (token, value) in context {
+            if !tokens.iter().any(|t| t == token) {
+                tokens.push(token.to_owned());
+                values.push(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::strings;
+
+    fn value_of<'a>(tokens: &[String], values: &'a [String], token: &str) -> &'a str {
+        let idx = tokens.iter().position(|t| t == token).unwrap();
+        &values[idx]
+    }
+
+    #[test]
+    fn extend_fills_reserved_tokens_of_the_line() {
+        let sequence = SequenceOptions {
+            start: 9,
+            padding: 3,
+        };
+        let context =
+            RowContext::new(&sequence, 12, &[5, 7]).with_numbers(strings(&["421/001", "421/002"]));
+        let (mut tokens, mut values) = (strings(&["{{nama}}"]), strings(&["Budi"]));
+
+        context.extend(1, &mut tokens, &mut values);
+
+        assert_eq!(value_of(&tokens, &values, TOKEN_SEQUENCE), "010");
+        assert_eq!(value_of(&tokens, &values, TOKEN_TOTAL), "12");
+        assert_eq!(value_of(&tokens, &values, TOKEN_SOURCE_ROW), "7");
+        assert_eq!(value_of(&tokens, &values, TOKEN_NUMBER), "421/002");
+        assert_eq!(value_of(&tokens, &values, TOKEN_REVISION), "");
+        assert!(!value_of(&tokens, &values, TOKEN_TIMESTAMP).is_empty());
+    }
+
+    #[test]
+    fn extend_keeps_tokens_given_already() {
+        let context = RowContext::new(&SequenceOptions::default(), 3, &[]);
+        let mut tokens = strings(&["{{nama}}", TOKEN_SEQUENCE]);
+        let mut values = strings(&["Budi", "A-1"]);
+
+        context.extend(2, &mut tokens, &mut values);
+
+        assert_eq!(value_of(&tokens, &values, TOKEN_SEQUENCE), "A-1");
+        assert_eq!(value_of(&tokens, &values, TOKEN_SOURCE_ROW), "3");
+        assert_eq!(tokens.len(), values.len());
+        // no numbering, the number is empty
+        assert_eq!(value_of(&tokens, &values, TOKEN_NUMBER), "");
+    }
+
+    #[test]
+    fn part_numbers_start_at_its_offset() {
+        let context = RowContext::new(&SequenceOptions::default(), 0, &[]).without_total();
+        let part = context.part(256, strings(&["N-257", "N-258"]), vec![300, 302]);
+        let (mut tokens, mut values) = (Vec::new(), Vec::new());
+
+        part.extend(257, &mut tokens, &mut values);
+
+        assert_eq!(part.number(255), None);
+        assert_eq!(part.number(256), Some("N-257"));
+        assert_eq!(part.source_row(255), 256);
+        assert_eq!(part.source_row(256), 300);
+        assert_eq!(value_of(&tokens, &values, TOKEN_SOURCE_ROW), "302");
+        assert_eq!(value_of(&tokens, &values, TOKEN_NUMBER), "N-258");
+        assert_eq!(value_of(&tokens, &values, TOKEN_SEQUENCE), "258");
+        assert_eq!(value_of(&tokens, &values, TOKEN_TOTAL), "");
+    }
+
+    #[test]
+    fn timestamp_is_the_generation_time() {
+        let time = time::macros::datetime!(2026-10-19 7:05 UTC);
+        let context = RowContext::new(&SequenceOptions::default(), 1, &[]).with_timestamp(time);
+        let (mut tokens, mut values) = (Vec::new(), Vec::new());
+
+        context.extend(0, &mut tokens, &mut values);
+
+        let timestamp = value_of(&tokens, &values, TOKEN_TIMESTAMP);
+        assert_eq!(timestamp, "19-10-2026 07.05");
+    }
+
+    #[test]
+    fn reserved_tokens_start_with_underscore() {
+        assert!(is_reserved(TOKEN_NUMBER));
+        assert!(is_reserved("{{_anything}}"));
+        assert!(!is_reserved("{{nama}}"));
+    }
+}
@@ -0,0 +1,201 @@
+use super::{DocxError, DocxResult};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extension of the converted documents.
+pub const PDF_EXTENSION: &str = "pdf";
+
+/// Converter of the generated DOCX files into PDF.
+pub trait Converter: Debug + Send + Sync {
+    /// Converts the DOCX files into PDF files next to them (see [`pdf_path`]).
+    /// Returns path of the PDF or the failure for each of the files, in order of the files.
+    fn convert(&self, files: &[PathBuf]) -> Vec<DocxResult<PathBuf>>;
+}
+
+/// Returns path of the PDF converted from the DOCX file (`piagam.docx` -> `piagam.pdf`).
+pub fn pdf_path(docx: &Path) -> PathBuf {
+    docx.with_extension(PDF_EXTENSION)
+}
+
+/// Converter driving locally installed LibreOffice in headless mode.
+#[derive(Debug, Clone)]
+pub struct LibreOfficeConverter {
+    /// LibreOffice executable.
+    pub program: PathBuf,
+
+    /// count of the files converted by a single LibreOffice process.
+    pub batch_size: usize,
+}
+
+impl Default for LibreOfficeConverter {
+    fn default() -> Self {
+        Self {
+            program: default_program(),
+            batch_size: 50,
+        }
+    }
+}
+
+impl LibreOfficeConverter {
+    /// Converts the files of the same folder by a single LibreOffice process.
+    fn convert_batch(&self, folder: &Path, files: &[&PathBuf]) -> Vec<DocxResult<PathBuf>> {
+        // stale PDFs would be taken for the converted ones
+        for file in files.iter() {
+            if let Err(err) = fs::remove_file(pdf_path(file)) {
+                if err.kind() != ErrorKind::NotFound {
+                    return files
+                        .iter()
+                        .map(|_| Err(io::Error::new(err.kind(), err.to_string()).into()))
+                        .collect();
+                }
+            }
+        }
+
+        let output = Command::new(&self.program)
+            .arg("--headless")
+            .arg("--convert-to")
+            .arg(PDF_EXTENSION)
+            .arg("--outdir")
+            .arg(folder)
+            .args(files)
+            .output();
+        let details = match output {
+            Ok(output) if output.status.success() => String::new(),
+            Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            Err(err) => {
+                return files
+                    .iter()
+                    .map(|_| {
+                        Err(DocxError::ConverterUnavailable {
+                            program: self.program.clone(),
+                            details: err.to_string(),
+                        })
+                    })
+                    .collect();
+            }
+        };
+        files
+            .iter()
+            .map(|file| {
+                let pdf = pdf_path(file);
+                if pdf.exists() {
+                    Ok(pdf)
+                } else {
+                    Err(conversion_error(file, &details))
+                }
+            })
+            .collect()
+    }
+}
+
+impl Converter for LibreOfficeConverter {
+    fn convert(&self, files: &[PathBuf]) -> Vec<DocxResult<PathBuf>> {
+        let mut folders: BTreeMap<PathBuf, Vec<usize>> = Default::default();
+        for (idx, file) in files.iter().enumerate() {
+            folders.entry(output_folder(file)).or_default().push(idx);
+        }
+
+        let mut results: Vec<Option<DocxResult<PathBuf>>> = files.iter().map(|_| None).collect();
+        for (folder, indexes) in folders.iter() {
+            for batch in indexes.chunks(self.batch_size.max(1)) {
+                let batch_files: Vec<&PathBuf> = batch.iter().map(|idx| &files[*idx]).collect();
+                let converted = self.convert_batch(folder, &batch_files);
+                for (idx, result) in batch.iter().zip(converted) {
+                    results[*idx] = Some(result);
+                }
+            }
+        }
+        results
+            .into_iter()
+            .zip(files)
+            .map(|(result, file)| result.unwrap_or_else(|| Err(conversion_error(file, ""))))
+            .collect()
+    }
+}
+
+/// Returns the folder the PDF of the file is written into - the folder of the file
+/// (the working directory for a bare file name).
+fn output_folder(file: &Path) -> PathBuf {
+    match file.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Returns the LibreOffice executable - the one of the default installation when it exists,
+/// otherwise `soffice` is looked up in the `PATH`.
+fn default_program() -> PathBuf {
+    let installed = PathBuf::from(r"C:\Program Files\LibreOffice\program\soffice.exe");
+    if installed.exists() {
+        installed
+    } else {
+        PathBuf::from("soffice")
+    }
+}
+
+/// Returns error of the file which failed to be converted.
+pub(super) fn conversion_error(file: &Path, details: &str) -> DocxError {
+    DocxError::ConversionFailed {
+        path: file.to_path_buf(),
+        details: details.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{FakeConverter, TempDir};
+
+    #[test]
+    fn pdf_is_next_to_the_document() {
+        let pdf = pdf_path(Path::new("out/6A/Budi.rev1.docx"));
+        assert_eq!(pdf, Path::new("out/6A/Budi.rev1.pdf"));
+        let folder = output_folder(Path::new("out/6A/Budi.docx"));
+        assert_eq!(folder, Path::new("out/6A"));
+        assert_eq!(output_folder(Path::new("Budi.docx")), Path::new("."));
+    }
+
+    #[test]
+    fn missing_converter_fails_each_file_and_removes_stale_pdfs() {
+        let dir = TempDir::new("convert-missing");
+        fs::create_dir_all(dir.join("6A")).unwrap();
+        let files = ["Budi.docx", "6A/Ani.docx", "Citra.docx"].map(|name| dir.join(name));
+        fs::write(dir.join("Budi.pdf"), "stale").unwrap();
+        let converter = LibreOfficeConverter {
+            program: dir.join("missing-soffice"),
+            batch_size: 1,
+        };
+
+        let results = converter.convert(&files);
+
+        assert_eq!(results.len(), files.len());
+        for result in results {
+            let unavailable = matches!(result, Err(DocxError::ConverterUnavailable { program, .. })
+                if program == dir.join("missing-soffice"));
+            assert!(unavailable);
+        }
+        assert!(!dir.join("Budi.pdf").exists());
+    }
+
+    #[test]
+    fn fake_converter_writes_pdfs_of_the_files_not_failing() {
+        let dir = TempDir::new("convert-fake");
+        let files = vec![dir.join("Budi.docx"), dir.join("Ani.docx")];
+        let mut converter = FakeConverter::default();
+        converter.failing.insert(files[1].clone());
+
+        let results = converter.convert(&files);
+
+        assert_eq!(results[0].as_ref().unwrap(), &dir.join("Budi.pdf"));
+        let failed = matches!(&results[1], Err(DocxError::ConversionFailed { path, .. })
+            if path == &files[1]);
+        assert!(failed);
+        assert_eq!(converter.converted(), files[..1]);
+        let pdf = fs::read_to_string(dir.join("Budi.pdf")).unwrap();
+        assert!(pdf.starts_with("%PDF"));
+    }
+}
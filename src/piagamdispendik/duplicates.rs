@@ -0,0 +1,409 @@
+use super::registry::{self, CertificateStatus, IssuedCertificate};
+use super::{context, BatchOptions, DocxResult, TokenPackArg, ValuePack};
+use crate::lang;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Configuration of the duplicate recipients detection.
+///
+/// Recipients are the same when all the key tokens have equal (normalized) values,
+/// and their names differ by no more than `max_typos` edits.
+/// With neither key tokens nor name token set, all the data tokens are compared.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateCheck {
+    /// tokens compared exactly, e.g. `{{nisn}}`, `{{nik}}` or `{{sekolah}}` and `{{lomba}}`.
+    #[serde(default)]
+    pub key_tokens: Vec<String>,
+
+    /// token of the recipient name, compared with tolerance to typos.
+    #[serde(default)]
+    pub name_token: Option<String>,
+
+    /// maximal count of single character edits two names may differ by to be considered the same.
+    #[serde(default)]
+    pub max_typos: usize,
+}
+
+/// What a row of the batch duplicates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuplicateOf {
+    /// earlier line of the same batch.
+    Line(usize),
+    /// certificate issued already.
+    Issued(Box<IssuedCertificate>),
+}
+
+/// Single duplicate recipient found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    /// line of the batch values.
+    pub line: usize,
+    pub of: DuplicateOf,
+    /// whether the names are only similar (not equal).
+    pub similar: bool,
+    /// values identifying the recipient of the line.
+    pub recipient: String,
+    /// values identifying the recipient of the duplicated line or certificate.
+    pub other_recipient: String,
+}
+
+impl Duplicate {
+    /// Returns localized textual representation of the duplicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_rows` - source data row number of each line (line number is used when missing)
+    pub fn to_message(&self, source_rows: &[usize]) -> String {
+        let row = |line: usize| source_rows.get(line).copied().unwrap_or(line + 1);
+        let mut args: lang::TrArgVec = vec![
+            ("row".to_string(), row(self.line).to_string()),
+            ("recipient".to_string(), self.recipient.clone()),
+            ("other_recipient".to_string(), self.other_recipient.clone()),
+        ];
+        let msg_id = match (&self.of, self.similar) {
+            (DuplicateOf::Line(other), similar) => {
+                args.push(("other".to_string(), row(*other).to_string()));
+                if similar {
+                    "duplicate-row-similar"
+                } else {
+                    "duplicate-row"
+                }
+            }
+            (DuplicateOf::Issued(certificate), similar) => {
+                args.push(("number".to_string(), certificate.number.clone()));
+                args.push(("date".to_string(), certificate.issued_at.clone()));
+                if similar {
+                    "duplicate-issued-similar"
+                } else {
+                    "duplicate-issued"
+                }
+            }
+        };
+        lang::tr_with_args(msg_id, &args)
+    }
+}
+
+/// Duplicate recipients found in a batch, to be reviewed before the generation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DuplicateReport {
+    pub duplicates: Vec<Duplicate>,
+}
+
+impl DuplicateReport {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+    }
+
+    /// Returns the lines found to be duplicates (the first occurrence is never included).
+    pub fn lines(&self) -> BTreeSet<usize> {
+        self.duplicates.iter().map(|d| d.line).collect()
+    }
+
+    /// Returns the items (values, source rows, ...) of the batch, without the duplicate lines.
+    pub fn exclude<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        let lines = self.lines();
+        items
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !lines.contains(idx))
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+}
+
+/// Recipient identification of a single line or issued certificate.
+struct RecipientKey {
+    exact: Vec<String>,
+    name: Option<String>,
+    display: String,
+}
+
+impl DuplicateCheck {
+    /// Looks for duplicate recipients within the batch, and against the certificates recorded
+    /// in the registry of the batch (see [`BatchOptions::registration`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `values` - vector of vector of values, one vector per document
+    /// * `options` - batch configuration (constants and registration are used)
+    ///
+    /// # Errors
+    ///
+    /// Can return errors when the registry cannot be read.
+    pub fn check(
+        &self,
+        tokens: TokenPackArg,
+        values: &[ValuePack],
+        options: &BatchOptions,
+    ) -> DocxResult<DuplicateReport> {
+        self.with_default_keys(tokens).find(tokens, values, options)
+    }
+
+    fn find(
+        &self,
+        tokens: TokenPackArg,
+        values: &[ValuePack],
+        options: &BatchOptions,
+    ) -> DocxResult<DuplicateReport> {
+        let issued = match &options.registration {
+            Some(registration) => registration.registry.certificates()?,
+            None => Vec::new(),
+        };
+        let lines: Vec<Option<RecipientKey>> = values
+            .iter()
+            .map(|row| {
+                let (tokens, values) = options.constants.merge(tokens, row);
+                self.key(|token| {
+                    tokens
+                        .iter()
+                        .position(|t| t == token)
+                        .and_then(|idx| values.get(idx).cloned())
+                })
+            })
+            .collect();
+        let issued: Vec<(IssuedCertificate, RecipientKey)> = issued
+            .into_iter()
+            .filter(|c| c.status == CertificateStatus::Active)
+            .filter_map(|c| {
+                let key = self.key(|token| {
+                    c.values
+                        .get(token)
+                        .or_else(|| c.recipient.get(token))
+                        .cloned()
+                });
+                key.map(|key| (c, key))
+            })
+            .collect();
+
+        let mut groups: BTreeMap<&[String], Vec<usize>> = Default::default();
+        let mut report = DuplicateReport::default();
+        for (line, key) in lines.iter().enumerate() {
+            let key = match key {
+                Some(key) => key,
+                None => continue,
+            };
+            let earlier = groups.entry(&key.exact).or_default();
+            let found = earlier
+                .iter()
+                .filter_map(|other| {
+                    let other_key = lines[*other].as_ref()?;
+                    let similar = self.similar(key, other_key)?;
+                    Some((DuplicateOf::Line(*other), similar, &other_key.display))
+                })
+                .chain(issued.iter().filter_map(|(certificate, other_key)| {
+                    if other_key.exact != key.exact {
+                        return None;
+                    }
+                    let similar = self.similar(key, other_key)?;
+                    Some((
+                        DuplicateOf::Issued(Box::new(certificate.clone())),
+                        similar,
+                        &other_key.display,
+                    ))
+                }))
+                .min_by_key(|(_, similar, _)| *similar);
+            match found {
+                Some((of, similar, other_recipient)) => report.duplicates.push(Duplicate {
+                    line,
+                    of,
+                    similar,
+                    recipient: key.display.clone(),
+                    other_recipient: other_recipient.clone(),
+                }),
+                None => earlier.push(line),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Builds recipient identification out of the token values, `None` for blank ones
+    /// or when some of the key tokens has no value at all.
+    fn key<F: Fn(&str) -> Option<String>>(&self, value_of: F) -> Option<RecipientKey> {
+        let exact: Vec<String> = self
+            .key_tokens
+            .iter()
+            .map(|token| value_of(token).map(|value| registry::normalize(&value)))
+            .collect::<Option<_>>()?;
+        let name = match &self.name_token {
+            Some(token) => Some(registry::normalize(&value_of(token)?)),
+            None => None,
+        };
+        if exact
+            .iter()
+            .chain(name.iter())
+            .all(|value| value.is_empty())
+        {
+            return None;
+        }
+        let display = name
+            .iter()
+            .chain(exact.iter())
+            .filter(|value| !value.is_empty())
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(", ");
+        Some(RecipientKey {
+            exact,
+            name,
+            display,
+        })
+    }
+
+    /// Returns `Some(false)` for the same names, `Some(true)` for similar ones, `None` otherwise.
+    fn similar(&self, key: &RecipientKey, other: &RecipientKey) -> Option<bool> {
+        match (&key.name, &other.name) {
+            (Some(name), Some(other)) if name == other => Some(false),
+            (Some(name), Some(other)) if edit_distance(name, other) <= self.max_typos => Some(true),
+            (Some(_), Some(_)) => None,
+            _ => Some(false),
+        }
+    }
+
+    /// Returns the check with all the data tokens as key tokens, when none are configured.
+    fn with_default_keys(&self, tokens: TokenPackArg) -> Self {
+        if !self.key_tokens.is_empty() || self.name_token.is_some() {
+            return self.clone();
+        }
+        Self {
+            key_tokens: tokens
+                .iter()
+                .filter(|token| !context::is_reserved(token))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Levenshtein distance of the two strings (count of single character insertions, deletions and substitutions).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::registry::{CertificateRegistry, Registration};
+    use crate::piagamdispendik::testing::{strings, TempDir};
+    use std::path::Path;
+
+    fn name_check(max_typos: usize) -> DuplicateCheck {
+        DuplicateCheck {
+            key_tokens: strings(&["{{sekolah}}"]),
+            name_token: Some("{{nama}}".to_owned()),
+            max_typos,
+        }
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("budi", "budi"), 0);
+        assert_eq!(edit_distance("budi", "budy"), 1);
+        assert_eq!(edit_distance("budi", "bdi"), 1);
+        assert_eq!(edit_distance("budi", "budii"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "ani"), 3);
+        assert_eq!(edit_distance("šárka", "sarka"), 2);
+    }
+
+    #[test]
+    fn check_finds_equal_and_similar_names_of_the_same_key() {
+        let tokens = strings(&["{{nama}}", "{{sekolah}}"]);
+        let values = vec![
+            strings(&["Budi Santoso", "SDN 1"]),
+            strings(&["budi  santoso", "sdn 1"]),
+            strings(&["Budi Santosa", "SDN 1"]),
+            strings(&["Budi Santoso", "SDN 2"]),
+            strings(&["Ani", "SDN 1"]),
+        ];
+
+        let report = name_check(1)
+            .check(&tokens, &values, &BatchOptions::default())
+            .unwrap();
+        assert_eq!(report.lines().into_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(report.duplicates[0].of, DuplicateOf::Line(0));
+        assert!(!report.duplicates[0].similar);
+        assert_eq!(report.duplicates[1].of, DuplicateOf::Line(0));
+        assert!(report.duplicates[1].similar);
+        assert_eq!(report.exclude(&[0, 1, 2, 3, 4]), vec![0, 3, 4]);
+
+        let report = name_check(0)
+            .check(&tokens, &values, &BatchOptions::default())
+            .unwrap();
+        assert_eq!(report.lines().into_iter().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn check_compares_all_data_tokens_by_default() {
+        let tokens = strings(&["{{nama}}", "{{sekolah}}", context::TOKEN_SEQUENCE]);
+        let values = vec![
+            strings(&["Budi", "SDN 1", "1"]),
+            strings(&["Budi", "SDN 1", "2"]),
+            strings(&["", "", "3"]),
+            strings(&["", "", "4"]),
+        ];
+
+        let report = DuplicateCheck::default()
+            .check(&tokens, &values, &BatchOptions::default())
+            .unwrap();
+        assert_eq!(report.lines().into_iter().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn check_finds_active_issued_certificates() {
+        let dir = TempDir::new("duplicates-issued");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let certificate = |name: &str, number: &str| {
+            let mut certificate = IssuedCertificate::with_digest(
+                Path::new("piagam.docx"),
+                Path::new("piagam.docx"),
+                number.to_owned(),
+                Some(number),
+                (&strings(&["{{nama}}"]), &strings(&[name])),
+            );
+            certificate.values = [("{{sekolah}}", "SDN 1"), ("{{nama}}", name)]
+                .iter()
+                .map(|(token, value)| (token.to_string(), value.to_string()))
+                .collect();
+            certificate
+        };
+        registry
+            .record(vec![
+                certificate("Budi", "421/001"),
+                certificate("Ani", "421/002"),
+            ])
+            .unwrap();
+        registry.revoke("421/002", "batal").unwrap();
+        let options = BatchOptions {
+            registration: Some(Registration {
+                registry,
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+
+        let tokens = strings(&["{{nama}}", "{{sekolah}}"]);
+        let values = vec![strings(&["Budy", "SDN 1"]), strings(&["Ani", "SDN 1"])];
+        let report = name_check(1).check(&tokens, &values, &options).unwrap();
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].line, 0);
+        assert!(report.duplicates[0].similar);
+        assert!(matches!(
+            &report.duplicates[0].of,
+            DuplicateOf::Issued(c) if c.number == "421/001"
+        ));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::lang;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Error returned on failure of some of the docx-filler methods.
+/// The variants keep what specifically went wrong (line, column, token, file...),
+/// the message is localized only when the error is displayed.
+#[derive(Debug, thiserror::Error)]
+pub enum DocxError {
+    Io(#[from] std::io::Error),
+    Zip(#[from] zip::result::ZipError),
+    CsvError(#[from] csv::Error),
+    Json(#[from] serde_json::Error),
+
+    /// no tokens to be filled.
+    NoTokens,
+    /// token given more than once.
+    DuplicateToken {
+        token: String,
+    },
+    /// no values to be filled.
+    MissingInput,
+    /// count of the values differs from count of the tokens.
+    CountMismatch {
+        tokens: usize,
+        values: usize,
+    },
+    /// values of the line (numbered from 1) are not valid.
+    Line {
+        line: usize,
+        #[source]
+        source: Box<DocxError>,
+    },
+    /// output file name does not end with `.docx`.
+    NoDocxSuffix {
+        filename: String,
+    },
+    /// multiple lines of the batch would have the same output file.
+    SameOutputFile {
+        filename: String,
+    },
+    /// output file exists already and must not be overwritten.
+    OutputExists {
+        path: PathBuf,
+    },
+    /// multiple lines of the batch would have the same file in the archive.
+    SameArchiveEntry {
+        filename: String,
+        archive: PathBuf,
+    },
+    MissingArchivePattern,
+    MissingManifest,
+    /// merged and archived batches are not journaled into a manifest, so they cannot be resumed.
+    ManifestUnsupported,
+    /// token the merged documents are grouped by is not filled in the batch.
+    UnknownGroupToken {
+        token: String,
+    },
+    /// column required by the mapping profile is missing in the input data.
+    MissingColumn {
+        column: String,
+    },
+    /// line (numbered from 1) of the constants file is not in `token = value` form.
+    InvalidConstantsLine {
+        line: usize,
+    },
+    /// no active certificate of the number or SHA-256 in the registry.
+    NoActiveCertificate {
+        id: String,
+    },
+    /// certificate stored in an archive cannot be reissued (the path is within the archive).
+    ArchivedCertificate {
+        path: PathBuf,
+    },
+    /// part of the DOCX package is missing.
+    MissingPart {
+        part: String,
+    },
+    /// part of the DOCX package is not a valid UTF-8 text.
+    InvalidPart {
+        part: String,
+    },
+    /// part is written into the DOCX package twice.
+    DuplicatePart {
+        part: String,
+    },
+    /// document part has no body to be merged.
+    NoBody,
+    /// part (header, footer, properties...) of the merged document would be filled differently
+    /// by the lines merged together, while the merged document has it only once.
+    MergedPartDiffers {
+        part: String,
+    },
+    /// store is locked by another generation.
+    StoreLocked {
+        path: PathBuf,
+    },
+    /// input data file is neither CSV nor Excel workbook.
+    UnsupportedData {
+        path: PathBuf,
+    },
+    /// Excel workbook cannot be read.
+    Excel {
+        path: PathBuf,
+        details: String,
+    },
+    /// converter failed to convert the file.
+    ConversionFailed {
+        path: PathBuf,
+        details: String,
+    },
+    /// converter program could not be started.
+    ConverterUnavailable {
+        program: PathBuf,
+        details: String,
+    },
+    /// rows (source data row number and the error) of the batch failed,
+    /// the documents generated may not have been recorded either.
+    RowsFailed {
+        rows: Vec<(usize, String)>,
+        record_failure: Option<String>,
+    },
+    /// documents were generated, but recording them into the registries failed.
+    NotRecorded {
+        details: String,
+    },
+}
+
+impl fmt::Display for DocxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arg = |name: &str, value: String| (name.to_string(), value);
+        let message = match self {
+            DocxError::Io(err) => tr("error-io", vec![arg("details", err.to_string())]),
+            DocxError::Zip(err) => tr("error-zip", vec![arg("details", err.to_string())]),
+            DocxError::CsvError(err) => tr("error-csv", vec![arg("details", err.to_string())]),
+            DocxError::Json(err) => tr("error-json", vec![arg("details", err.to_string())]),
+            DocxError::NoTokens => lang::tr("valid-no-tokens"),
+            DocxError::DuplicateToken { token } => {
+                tr("valid-token-duplicity", vec![arg("token", token.clone())])
+            }
+            DocxError::MissingInput => lang::tr("valid-missing-input"),
+            DocxError::CountMismatch { tokens, values } => tr(
+                "valid-count-mismatch",
+                vec![
+                    arg("tokens", tokens.to_string()),
+                    arg("values", values.to_string()),
+                ],
+            ),
+            DocxError::Line { line, source } => tr(
+                "valid-line-mismatch",
+                vec![
+                    arg("line", line.to_string()),
+                    arg("details", source.to_string()),
+                ],
+            ),
+            DocxError::NoDocxSuffix { filename } => tr(
+                "valid-no-docx-suffix",
+                vec![arg("filename", filename.clone())],
+            ),
+            DocxError::SameOutputFile { filename } => tr(
+                "valid-same-output-filename",
+                vec![arg("filename", filename.clone())],
+            ),
+            DocxError::OutputExists { path } => tr(
+                "docx-filler-fail-overwrite",
+                vec![arg("filename", path.display().to_string())],
+            ),
+            DocxError::SameArchiveEntry { filename, archive } => tr(
+                "archive-same-entry",
+                vec![
+                    arg("filename", filename.clone()),
+                    arg("archive", archive.display().to_string()),
+                ],
+            ),
+            DocxError::MissingArchivePattern => lang::tr("archive-missing-pattern"),
+            DocxError::MissingManifest => lang::tr("manifest-missing"),
+            DocxError::ManifestUnsupported => lang::tr("manifest-unsupported"),
+            DocxError::UnknownGroupToken { token } => tr(
+                "merge-unknown-group-token",
+                vec![arg("token", token.clone())],
+            ),
+            DocxError::MissingColumn { column } => tr(
+                "profile-missing-column",
+                vec![arg("column", column.clone())],
+            ),
+            DocxError::InvalidConstantsLine { line } => tr(
+                "constants-invalid-line",
+                vec![arg("line", line.to_string())],
+            ),
+            DocxError::NoActiveCertificate { id } => tr(
+                "registry-no-active-certificate",
+                vec![arg("id", id.clone())],
+            ),
+            DocxError::ArchivedCertificate { path } => tr(
+                "registry-archived-certificate",
+                vec![arg("filename", path.display().to_string())],
+            ),
+            DocxError::MissingPart { part } => {
+                tr("error-missing-part", vec![arg("part", part.clone())])
+            }
+            DocxError::InvalidPart { part } => {
+                tr("error-invalid-part", vec![arg("part", part.clone())])
+            }
+            DocxError::DuplicatePart { part } => {
+                tr("package-duplicate-part", vec![arg("part", part.clone())])
+            }
+            DocxError::NoBody => lang::tr("merge-no-body"),
+            DocxError::MergedPartDiffers { part } => {
+                tr("merge-part-differs", vec![arg("part", part.clone())])
+            }
+            DocxError::StoreLocked { path } => tr(
+                "store-locked",
+                vec![arg("filename", path.display().to_string())],
+            ),
+            DocxError::UnsupportedData { path } => tr(
+                "error-unsupported-data",
+                vec![arg("filename", path.display().to_string())],
+            ),
+            DocxError::Excel { path, details } => tr(
+                "error-xlsx",
+                vec![
+                    arg("filename", path.display().to_string()),
+                    arg("details", details.clone()),
+                ],
+            ),
+            DocxError::ConversionFailed { path, details } => tr(
+                "convert-failed",
+                vec![
+                    arg("filename", path.display().to_string()),
+                    arg("details", details.clone()),
+                ],
+            ),
+            DocxError::ConverterUnavailable { program, details } => tr(
+                "convert-no-libreoffice",
+                vec![
+                    arg("program", program.display().to_string()),
+                    arg("details", details.clone()),
+                ],
+            ),
+            DocxError::RowsFailed {
+                rows,
+                record_failure,
+            } => {
+                rows.iter()
+                    .map(|(row, error)| {
+                        tr(
+                            "batch-row-failed",
+                            vec![arg("row", row.to_string()), arg("error", error.clone())],
+                        )
+                    })
+                    .chain(record_failure.iter().map(|details| {
+                        tr("batch-not-recorded", vec![arg("details", details.clone())])
+                    }))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            }
+            DocxError::NotRecorded { details } => {
+                tr("batch-not-recorded", vec![arg("details", details.clone())])
+            }
+        };
+        f.write_str(&message)
+    }
+}
+
+fn tr(msg_id: &str, args: lang::TrArgVec) -> String {
+    lang::tr_with_args(msg_id, &args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn wrapped_errors_are_the_source() {
+        let io = DocxError::from(io::Error::other("disk full"));
+        assert!(matches!(io, DocxError::Io(_)));
+        assert_eq!(io.source().unwrap().to_string(), "disk full");
+        assert!(io.to_string().contains("disk full"));
+
+        let zip = DocxError::from(zip::result::ZipError::FileNotFound);
+        let expected = zip::result::ZipError::FileNotFound.to_string();
+        assert_eq!(zip.source().unwrap().to_string(), expected);
+
+        let record = csv::Reader::from_reader("a,b\n1\n".as_bytes())
+            .records()
+            .next()
+            .unwrap();
+        let csv = DocxError::from(record.unwrap_err());
+        assert!(matches!(csv, DocxError::CsvError(_)));
+        assert!(csv.source().is_some());
+    }
+
+    #[test]
+    fn line_error_keeps_the_cause() {
+        let cause = DocxError::MissingInput;
+        let cause_message = cause.to_string();
+        let err = DocxError::Line {
+            line: 4,
+            source: Box::new(cause),
+        };
+
+        assert_eq!(err.source().unwrap().to_string(), cause_message);
+        assert!(err.to_string().contains(&cause_message));
+        assert!(DocxError::NoTokens.source().is_none());
+    }
+
+    #[test]
+    fn failed_rows_are_displayed_one_per_line() {
+        let err = DocxError::RowsFailed {
+            rows: vec![(2, "first".to_owned()), (5, "second".to_owned())],
+            record_failure: Some("registry locked".to_owned()),
+        };
+        let message = err.to_string();
+        let lines: Vec<&str> = message.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("first") && lines[1].contains("second"));
+        assert!(lines[2].contains("registry locked"));
+        let not_recorded = DocxError::NotRecorded {
+            details: "registry locked".to_owned(),
+        };
+        assert_eq!(not_recorded.to_string(), lines[2]);
+    }
+}
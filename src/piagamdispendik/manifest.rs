@@ -0,0 +1,309 @@
+use super::overwrite::OverwritePolicy;
+use super::store;
+use super::DocxResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// File name suffix of the batch manifest stored alongside the DOCX template.
+pub const MANIFEST_EXTENSION: &str = "manifest.jsonl";
+
+/// Single record of the manifest journal (one JSON per line).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestEntry {
+    /// the batch was started or resumed.
+    Batch {
+        template: PathBuf,
+        output_pattern: String,
+        started_at: String,
+        /// what happens with the output files which exist already.
+        #[serde(default)]
+        overwrite: OverwritePolicy,
+    },
+    /// the line is going to be generated into the path.
+    Planned {
+        line: usize,
+        row: usize,
+        path: PathBuf,
+    },
+    /// the output file of the line is being written - `created` for the batch,
+    /// or existing one to be replaced.
+    Started {
+        line: usize,
+        path: PathBuf,
+        #[serde(default)]
+        created: bool,
+    },
+    /// the output file of the line existed already and was kept, as the overwrite policy says.
+    Skipped { line: usize, path: PathBuf },
+    /// the document of the line was generated (or the existing output file is the same already).
+    Completed {
+        line: usize,
+        path: PathBuf,
+        sha256: String,
+        #[serde(default)]
+        number: Option<String>,
+    },
+}
+
+/// Line of a batch completed before, as recorded in the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedLine {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub number: Option<String>,
+}
+
+/// Generation manifest of a batch - journal of the planned and completed lines appended as the batch
+/// progresses, so an interrupted batch can be resumed (see [`super::DocxTemplate::resume_docx_batch`]).
+#[derive(Debug)]
+pub struct Manifest {
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    /// Starts manifest of a new batch, replacing the manifest of a previous batch.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        template: &Path,
+        output_pattern: &str,
+        overwrite: OverwritePolicy,
+    ) -> DocxResult<Self> {
+        let manifest = Self {
+            file: Mutex::new(File::create(path.as_ref())?),
+        };
+        manifest.batch(template, output_pattern, overwrite)?;
+        Ok(manifest)
+    }
+
+    /// Opens manifest of an interrupted batch, to be continued.
+    pub fn resume<P: AsRef<Path>>(
+        path: P,
+        template: &Path,
+        output_pattern: &str,
+        overwrite: OverwritePolicy,
+    ) -> DocxResult<Self> {
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path.as_ref())?;
+        let manifest = Self {
+            file: Mutex::new(file),
+        };
+        manifest.batch(template, output_pattern, overwrite)?;
+        Ok(manifest)
+    }
+
+    /// Reads the manifest, returns the lines completed whose files are still intact (same content as generated).
+    /// Lines interrupted in the middle of writing leave no output behind - their temporary files are removed,
+    /// and so are the output files created for them (existing files being replaced are kept).
+    pub fn load_completed<P: AsRef<Path>>(path: P) -> DocxResult<BTreeMap<usize, CompletedLine>> {
+        let file = match File::open(path.as_ref()) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Default::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut started: BTreeMap<usize, (PathBuf, bool)> = Default::default();
+        let mut completed: BTreeMap<usize, CompletedLine> = Default::default();
+        for line in BufReader::new(file).lines() {
+            // the last line may be cut off by the interruption
+            let entry: ManifestEntry = match serde_json::from_str(&line?) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            match entry {
+                ManifestEntry::Batch { .. } | ManifestEntry::Skipped { .. } => (),
+                ManifestEntry::Planned { line, .. } => {
+                    completed.remove(&line);
+                }
+                ManifestEntry::Started {
+                    line,
+                    path,
+                    created,
+                } => {
+                    completed.remove(&line);
+                    started.insert(line, (path, created));
+                }
+                ManifestEntry::Completed {
+                    line,
+                    path,
+                    sha256,
+                    number,
+                } => {
+                    started.remove(&line);
+                    completed.insert(
+                        line,
+                        CompletedLine {
+                            path,
+                            sha256,
+                            number,
+                        },
+                    );
+                }
+            }
+        }
+
+        for (path, created) in started.values() {
+            let mut leftovers = vec![store::sibling_path(path, "tmp")];
+            if *created {
+                leftovers.push(path.clone());
+            }
+            for leftover in leftovers.iter().filter(|leftover| leftover.exists()) {
+                fs::remove_file(leftover)?;
+            }
+        }
+        let mut intact: BTreeMap<usize, CompletedLine> = Default::default();
+        for (line, done) in completed {
+            match store::sha256_file(&done.path) {
+                Ok(sha256) if sha256 == done.sha256 => {
+                    intact.insert(line, done);
+                }
+                _ => (),
+            }
+        }
+        Ok(intact)
+    }
+
+    fn batch(
+        &self,
+        template: &Path,
+        output_pattern: &str,
+        overwrite: OverwritePolicy,
+    ) -> DocxResult<()> {
+        self.append(&ManifestEntry::Batch {
+            template: template.to_path_buf(),
+            output_pattern: output_pattern.to_owned(),
+            started_at: store::timestamp(store::now()),
+            overwrite,
+        })
+    }
+
+    pub fn planned(&self, line: usize, row: usize, path: &Path) -> DocxResult<()> {
+        self.append(&ManifestEntry::Planned {
+            line,
+            row,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Records that the output file of the line is being written - `created` for the batch,
+    /// or existing one to be replaced.
+    pub fn started(&self, line: usize, path: &Path, created: bool) -> DocxResult<()> {
+        self.append(&ManifestEntry::Started {
+            line,
+            path: path.to_path_buf(),
+            created,
+        })
+    }
+
+    pub fn skipped(&self, line: usize, path: &Path) -> DocxResult<()> {
+        self.append(&ManifestEntry::Skipped {
+            line,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Records the line completed, along with hash of its output file.
+    pub fn completed(&self, line: usize, path: &Path, number: Option<&str>) -> DocxResult<()> {
+        self.append(&ManifestEntry::Completed {
+            line,
+            path: path.to_path_buf(),
+            sha256: store::sha256_file(path)?,
+            number: number.map(|n| n.to_owned()),
+        })
+    }
+
+    fn append(&self, entry: &ManifestEntry) -> DocxResult<()> {
+        let mut record = serde_json::to_vec(entry)?;
+        record.push(b'\n');
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        file.write_all(&record)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns path of the batch manifest stored alongside the template (`piagam.docx` -> `piagam.manifest.jsonl`).
+pub fn manifest_path<P: AsRef<Path>>(template: P) -> PathBuf {
+    template.as_ref().with_extension(MANIFEST_EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::TempDir;
+
+    #[test]
+    fn load_completed_keeps_intact_lines_of_interrupted_batch() {
+        let dir = TempDir::new("manifest-interrupted");
+        let path = dir.join("piagam.manifest.jsonl");
+        let template = dir.join("piagam.docx");
+        let manifest =
+            Manifest::create(&path, &template, "{{nama}}.docx", Default::default()).unwrap();
+        let files = ["Budi.docx", "Ani.docx", "Citra.docx", "Dedi.docx"].map(|name| dir.join(name));
+        for (line, file) in files.iter().enumerate() {
+            manifest.planned(line, line + 2, file).unwrap();
+        }
+        manifest.started(0, &files[0], true).unwrap();
+        fs::write(&files[0], b"Budi").unwrap();
+        manifest.completed(0, &files[0], Some("001")).unwrap();
+        manifest.started(2, &files[2], true).unwrap();
+        fs::write(&files[2], b"Citra").unwrap();
+        manifest.completed(2, &files[2], None).unwrap();
+        // altered after the batch
+        fs::write(&files[2], b"Citra!").unwrap();
+        // interrupted while writing, with the record cut off
+        manifest.started(1, &files[1], true).unwrap();
+        fs::write(&files[1], b"").unwrap();
+        fs::write(store::sibling_path(&files[1], "tmp"), b"A").unwrap();
+        // interrupted while replacing existing file
+        fs::write(&files[3], b"Dedi").unwrap();
+        manifest.started(3, &files[3], false).unwrap();
+        fs::write(store::sibling_path(&files[3], "tmp"), b"D").unwrap();
+        drop(manifest);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"completed":{"line":1,"#).unwrap();
+
+        let completed = Manifest::load_completed(&path).unwrap();
+
+        let expected = CompletedLine {
+            path: files[0].clone(),
+            sha256: store::sha256_file(&files[0]).unwrap(),
+            number: Some("001".to_owned()),
+        };
+        assert_eq!(completed, [(0, expected)].into());
+        assert!(!files[1].exists());
+        assert!(files[2].exists());
+        assert_eq!(fs::read(&files[3]).unwrap(), b"Dedi");
+        for file in [&files[1], &files[3]] {
+            assert!(!store::sibling_path(file, "tmp").exists());
+        }
+    }
+
+    #[test]
+    fn resume_appends_to_the_manifest() {
+        let dir = TempDir::new("manifest-resume");
+        let path = dir.join("piagam.manifest.jsonl");
+        let template = dir.join("piagam.docx");
+        let file = dir.join("Budi.docx");
+        fs::write(&file, b"Budi").unwrap();
+        Manifest::create(&path, &template, "{{nama}}.docx", Default::default())
+            .unwrap()
+            .completed(0, &file, None)
+            .unwrap();
+
+        Manifest::resume(&path, &template, "{{nama}}.docx", Default::default()).unwrap();
+
+        assert!(Manifest::load_completed(&path).unwrap().contains_key(&0));
+        assert!(Manifest::load_completed(dir.join("missing.jsonl"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use super::{DocxError, DocxResult, TokenPack, ValuePack};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+lazy_static! {
+    /// Opening tag of the document body.
+    static ref BODY_START: Regex = Regex::new(r"<w:body(\s[^>]*)?>").expect("valid body regex");
+    /// Id of a drawing object, has to be unique within the whole document.
+    static ref DRAWING_ID: Regex =
+        Regex::new(r#"(<wp:docPr\b[^>]*?\sid=")(\d+)(")"#).expect("valid drawing id regex");
+    /// Id pairing bookmark start with its end, has to be unique within the whole document.
+    static ref BOOKMARK_ID: Regex =
+        Regex::new(r#"(<w:bookmark(?:Start|End)\b[^>]*?\sw:id=")(\d+)(")"#)
+            .expect("valid bookmark id regex");
+}
+
+/// Closing tag of the document body.
+const BODY_END: &str = "</w:body>";
+
+/// Closing tags of the body level blocks - the final section properties follow the last of them.
+const BLOCK_ENDS: &[&str] = &["</w:p>", "<w:p/>", "</w:tbl>", "</w:sdt>"];
+
+/// What separates documents of the lines in the merged document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeBreak {
+    /// section break - each document keeps its own page size, orientation, margins...
+    #[default]
+    Section,
+    /// page break - all the documents share the section properties of the template.
+    Page,
+}
+
+/// Configuration of a batch merged into single document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeOptions {
+    #[serde(default)]
+    pub separator: MergeBreak,
+
+    /// token the lines are grouped by - one merged document per each of its values,
+    /// e.g. `{{sekolah}}` (all the lines are merged into one document when not set).
+    #[serde(default)]
+    pub group_token: Option<String>,
+}
+
+impl MergeOptions {
+    /// Splits the lines (tokens and values of each line) into the groups merged together,
+    /// groups are in order of their first line.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::UnknownGroupToken when the group token is not among the tokens of the batch.
+    pub fn groups(&self, packs: &[(TokenPack, ValuePack)]) -> DocxResult<Vec<Vec<usize>>> {
+        let token = match &self.group_token {
+            Some(token) => token,
+            None => return Ok(vec![(0..packs.len()).collect()]),
+        };
+        let mut groups: Vec<Vec<usize>> = Default::default();
+        let mut group_of: HashMap<&str, usize> = Default::default();
+        for (idx, (tokens, values)) in packs.iter().enumerate() {
+            let value = tokens
+                .iter()
+                .position(|t| t == token)
+                .and_then(|position| values.get(position));
+            let value = match value {
+                Some(value) => value.as_str(),
+                None => {
+                    return Err(DocxError::UnknownGroupToken {
+                        token: token.clone(),
+                    })
+                }
+            };
+            match group_of.get(value) {
+                Some(group) => groups[*group].push(idx),
+                None => {
+                    group_of.insert(value, groups.len());
+                    groups.push(vec![idx]);
+                }
+            }
+        }
+        Ok(groups)
+    }
+}
+
+/// Document part of the rendered document split into its body content and the final section properties.
+struct SplitDocument<'a> {
+    /// XML preceding the body content, including the body opening tag.
+    head: &'a str,
+    content: &'a str,
+    /// final section properties of the body (empty when the document has none).
+    section: &'a str,
+    /// XML following the body content, from the body closing tag.
+    tail: &'a str,
+}
+
+impl<'a> SplitDocument<'a> {
+    fn split(document: &'a str) -> DocxResult<Self> {
+        let body_start = BODY_START.find(document).map(|start| start.end());
+        let body_end = document.rfind(BODY_END);
+        let (start, end) = match (body_start, body_end) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return Err(DocxError::NoBody),
+        };
+        let body = &document[start..end];
+        let blocks_end = BLOCK_ENDS
+            .iter()
+            .filter_map(|tag| body.rfind(tag).map(|position| position + tag.len()))
+            .max()
+            .unwrap_or(0);
+        let section = body[blocks_end..]
+            .find("<w:sectPr")
+            .map(|position| blocks_end + position)
+            .unwrap_or(body.len());
+        Ok(Self {
+            head: &document[..start],
+            content: &body[..section],
+            section: &body[section..],
+            tail: &document[end..],
+        })
+    }
+}
+
+/// Merges rendered document parts into a single document part, one after another.
+///
+/// All the documents are rendered from the same template, so their relationship ids (`r:embed` of images,
+/// `r:id` of hyperlinks, `w:headerReference`...) all point to the relationships of the template part -
+/// the merged document keeps them as they are, an id may be referenced any number of times.
+/// Ids which have to be unique within the document (drawings, bookmarks) are renumbered.
+pub(super) struct DocumentMerger {
+    separator: MergeBreak,
+    /// head, pending section properties and tail of the merged document (taken from the first document).
+    frame: Option<(String, String, String)>,
+    body: String,
+    drawings: usize,
+    bookmarks: usize,
+}
+
+impl DocumentMerger {
+    pub fn new(separator: MergeBreak) -> Self {
+        Self {
+            separator,
+            frame: None,
+            body: String::new(),
+            drawings: 0,
+            bookmarks: 0,
+        }
+    }
+
+    /// Appends the body of the rendered document part, separated from the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::NoBody when the document part has no body.
+    pub fn push(&mut self, document: &str) -> DocxResult<()> {
+        let split = SplitDocument::split(document)?;
+        match &mut self.frame {
+            None => {
+                self.frame = Some((
+                    split.head.to_owned(),
+                    split.section.to_owned(),
+                    split.tail.to_owned(),
+                ))
+            }
+            Some((_, previous_section, _)) => {
+                match self.separator {
+                    MergeBreak::Section if !previous_section.is_empty() => {
+                        self.body.push_str("<w:p><w:pPr>");
+                        self.body.push_str(previous_section);
+                        self.body.push_str("</w:pPr></w:p>");
+                    }
+                    _ => self
+                        .body
+                        .push_str(r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#),
+                }
+                if self.separator == MergeBreak::Section {
+                    *previous_section = split.section.to_owned();
+                }
+            }
+        }
+
+        let drawings = &mut self.drawings;
+        let content = DRAWING_ID.replace_all(split.content, |caps: &Captures| {
+            *drawings += 1;
+            format!("{}{}{}", &caps[1], drawings, &caps[3])
+        });
+        // start and end of a bookmark share the id, it is renumbered to the same one
+        let bookmarks = &mut self.bookmarks;
+        let mut renumbered: HashMap<String, usize> = Default::default();
+        let content = BOOKMARK_ID.replace_all(&content, |caps: &Captures| {
+            let id = renumbered.entry(caps[2].to_owned()).or_insert_with(|| {
+                *bookmarks += 1;
+                *bookmarks
+            });
+            format!("{}{}{}", &caps[1], id, &caps[3])
+        });
+        self.body.push_str(&content);
+        Ok(())
+    }
+
+    /// Returns the merged document part.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::NoBody when no document was pushed.
+    pub fn finish(self) -> DocxResult<String> {
+        let (head, section, tail) = self.frame.ok_or(DocxError::NoBody)?;
+        let mut document = head;
+        document.push_str(&self.body);
+        document.push_str(&section);
+        document.push_str(&tail);
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{document, paragraph};
+
+    fn merge(separator: MergeBreak, bodies: &[&str]) -> String {
+        let mut merger = DocumentMerger::new(separator);
+        for body in bodies {
+            merger.push(&document(body)).unwrap();
+        }
+        merger.finish().unwrap()
+    }
+
+    #[test]
+    fn documents_follow_each_other_separated_by_breaks() {
+        let bodies = [paragraph("Budi"), paragraph("Ani")];
+        let merged = merge(MergeBreak::Section, &[&bodies[0], &bodies[1]]);
+        let section =
+            r#"<w:sectPr><w:pgSz w:w="16838" w:h="11906" w:orient="landscape"/></w:sectPr>"#;
+        let separator = format!("<w:p><w:pPr>{}</w:pPr></w:p>", section);
+        let expected = paragraph("Budi") + &separator + &paragraph("Ani") + section;
+        assert!(merged.contains(&format!("<w:body>{}</w:body>", expected)));
+
+        let merged = merge(MergeBreak::Page, &[&bodies[0], &bodies[1]]);
+        assert!(merged.contains(r#"<w:br w:type="page"/>"#));
+        assert_eq!(merged.matches("<w:sectPr>").count(), 1);
+
+        assert!(matches!(
+            DocumentMerger::new(MergeBreak::Page).finish(),
+            Err(DocxError::NoBody)
+        ));
+        let mut merger = DocumentMerger::new(MergeBreak::Page);
+        let pushed = merger.push("<w:document/>");
+        assert!(matches!(pushed, Err(DocxError::NoBody)));
+    }
+
+    #[test]
+    fn unique_ids_are_renumbered() {
+        let body = concat!(
+            r#"<w:p><w:bookmarkStart w:id="0" w:name="nama"/><w:r><w:t>Budi</w:t></w:r>"#,
+            r#"<w:bookmarkEnd w:id="0"/><w:bookmarkStart w:id="7" w:name="kelas"/>"#,
+            r#"<w:bookmarkEnd w:id="7"/></w:p>"#,
+            r#"<w:p><w:r><w:drawing><wp:inline><wp:docPr id="1" name="Logo"/>"#,
+            r#"<a:blip r:embed="rId5"/></wp:inline></w:drawing></w:r></w:p>"#,
+        );
+
+        let merged = merge(MergeBreak::Page, &[body, body]);
+
+        let ids = |pattern: &Regex| -> Vec<String> {
+            let ids = pattern.captures_iter(&merged);
+            ids.map(|caps| caps[2].to_owned()).collect()
+        };
+        assert_eq!(ids(&BOOKMARK_ID), ["1", "1", "2", "2", "3", "3", "4", "4"]);
+        assert_eq!(ids(&DRAWING_ID), ["1", "2"]);
+        assert_eq!(merged.matches(r#"r:embed="rId5""#).count(), 2);
+    }
+}
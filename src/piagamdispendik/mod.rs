@@ -1,15 +1,55 @@
 #![allow(dead_code)]
 
+pub mod archive;
+pub mod compiled;
+pub mod constants;
+pub mod context;
+pub mod convert;
+pub mod duplicates;
+pub mod error;
+pub mod manifest;
+pub mod merge;
+pub mod numbering;
+pub mod overwrite;
+pub mod package;
+pub mod paths;
+pub mod profile;
+pub mod properties;
+pub mod registry;
+pub mod report;
+mod store;
 pub mod tokens;
 pub mod validations;
 pub mod xlsx_reader;
 
-use crate::lang;
-use regex::Regex;
-use std::io::{Read, Write};
+#[cfg(test)]
+mod testing;
+
+pub use error::DocxError;
+
+use archive::{ArchiveWriter, PlannedArchive, PlannedEntry};
+use compiled::CompiledPart;
+use constants::BatchConstants;
+use context::{RowContext, SequenceOptions};
+use convert::Converter;
+use manifest::{CompletedLine, Manifest};
+use merge::{DocumentMerger, MergeOptions};
+use numbering::Numbering;
+use overwrite::{OutputFile, OverwritePolicy};
+use package::{PackagePart, PackageWriter};
+use properties::DocumentProperties;
+use registry::Registration;
+use report::{BatchReport, RowOutcome, RowReport};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 use std::str::from_utf8;
-use std::{collections::HashMap, fs::File, path::PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::{collections::HashMap, path::PathBuf};
+use zip::DateTime;
 
 /// Alias for a set of tokens (placeholders).
 pub type TokenPack = Vec<String>;
@@ -19,31 +59,198 @@ pub type TokenPackArg<'a> = &'a [String];
 pub type ValuePack = Vec<String>;
 pub type ValuePackArg<'a> = &'a [String];
 
+/// Alias for a streamed set of values, along with number of its row in the source data.
+pub type StreamRow = (usize, ValuePack);
+
 type DocxResult<T> = Result<T, DocxError>;
 
-/// Error returned on failure of some of the docx-filler methods.
-/// String representation should give details on what specifically went wrong.
-#[derive(Debug, thiserror::Error)]
-pub enum DocxError {
-    #[error("IO error")]
-    Io(#[from] std::io::Error),
-    #[error("Ziping on file docx error")]
-    Zip(#[from] zip::result::ZipError),
-    #[error("Validation error: {0}")]
-    Validation(String),
-    #[error("Processing error: {0}")]
-    Processing(String),
-    #[error("Parsing csv file")]
-    CsvError(#[from] csv::Error),
-    
-    #[error("PiagamDispendikError {0}")]
-    ProagramDispendikError(String),
-
-    #[error("Unknown Erorr on PiagamDispendik Application")]
-    UnknownError,
+pub type FileMap = HashMap<String, Vec<u8>>;
+
+/// Count of the rows of a streamed batch read, validated and generated at once.
+const STREAM_PART_ROWS: usize = 256;
+
+/// Configuration of a batch generation, beside the tokens, values and output pattern.
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// values filled into their tokens in every document of the batch.
+    pub constants: BatchConstants,
+
+    /// formatting of the running number filled into [`context::TOKEN_SEQUENCE`].
+    pub sequence: SequenceOptions,
+
+    /// source data row number of each line of values (line number is used when empty).
+    pub source_rows: Vec<usize>,
+
+    /// certificate numbers allocation, filled into [`context::TOKEN_NUMBER`] (no numbering when not set).
+    pub numbering: Option<Numbering>,
+
+    /// registry the generated certificates are recorded into (not recorded when not set).
+    pub registration: Option<Registration>,
+
+    /// count of the documents generated in parallel (all the CPU cores are used when `0`).
+    pub workers: usize,
+
+    /// journal of the batch progress, needed to resume the batch when interrupted (no journal when not set).
+    /// Merged and archived batches cannot be resumed, they reject the manifest.
+    pub manifest: Option<PathBuf>,
+
+    /// what happens when output file of a document exists already.
+    pub overwrite: OverwritePolicy,
+
+    /// root directory of the output files, relative output patterns are resolved against it
+    /// (the working directory is used when not set).
+    pub output_dir: Option<PathBuf>,
+
+    /// archive file pattern - when set, the documents are written into ZIP archives instead of loose files,
+    /// lines filling the pattern to the same path share the archive (e.g. `{{sekolah}}.zip` for archive
+    /// per school, `piagam.zip` for single archive). The output pattern gives path within the archive.
+    pub archive_pattern: Option<String>,
+
+    /// converter of the generated documents into PDF (not converted when not set) - only the documents
+    /// generated as separate files are converted, results are given in the batch report.
+    pub converter: Option<Arc<dyn Converter>>,
 }
 
-pub type FileMap = HashMap<String, Vec<u8>>;
+/// Single document generated within a batch.
+#[derive(Debug, Clone)]
+struct GeneratedDocument {
+    /// certificate number assigned to the document.
+    number: Option<String>,
+    tokens: TokenPack,
+    values: ValuePack,
+    path: PathBuf,
+    /// SHA-256 of the document, when the path is not the document file itself (archived document).
+    sha256: Option<String>,
+}
+
+impl BatchOptions {
+    /// Creates the built-in row context of a batch having `total` lines of values.
+    fn row_context(&self, total: usize, numbers: Vec<String>) -> RowContext {
+        RowContext::new(&self.sequence, total, &self.source_rows).with_numbers(numbers)
+    }
+
+    /// Returns certificate numbers the documents of the batch would get, without reserving them.
+    fn preview_numbers(&self, total: usize) -> DocxResult<Vec<String>> {
+        match &self.numbering {
+            Some(numbering) => numbering.registry.preview(&numbering.format, total),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Records the generated documents into the number registry and issued-certificate registry.
+    fn record(&self, template: &DocxTemplate, documents: &[GeneratedDocument]) -> DocxResult<()> {
+        if let Some(numbering) = &self.numbering {
+            let files: Vec<(String, PathBuf)> = documents
+                .iter()
+                .filter_map(|d| d.number.clone().map(|number| (number, d.path.clone())))
+                .collect();
+            numbering.registry.record_files(&files)?;
+        }
+        if let Some(registration) = &self.registration {
+            registration.record(template, documents)?;
+        }
+        Ok(())
+    }
+
+    /// Reserves certificate numbers for the documents of the batch.
+    fn reserve_numbers(&self, total: usize) -> DocxResult<Vec<String>> {
+        match &self.numbering {
+            Some(numbering) => Ok(numbering
+                .registry
+                .reserve(&numbering.format, total)?
+                .into_iter()
+                .map(|assignment| assignment.number)
+                .collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Opens the manifest of the batch, if configured - new one, or the one of the interrupted batch to resume.
+    fn open_manifest(
+        &self,
+        template: &Path,
+        output_pattern: &str,
+        resume: bool,
+    ) -> DocxResult<Option<Manifest>> {
+        let manifest = match (&self.manifest, resume) {
+            (Some(path), false) => {
+                Manifest::create(path, template, output_pattern, self.overwrite)?
+            }
+            (Some(path), true) => Manifest::resume(path, template, output_pattern, self.overwrite)?,
+            (None, _) => return Ok(None),
+        };
+        Ok(Some(manifest))
+    }
+
+    /// Returns output path of a document of the batch (see [`paths::output_path`]).
+    fn output_path(
+        &self,
+        output_pattern: &str,
+        tokens: TokenPackArg,
+        values: ValuePackArg,
+    ) -> PathBuf {
+        paths::output_path(self.output_dir.as_deref(), output_pattern, tokens, values)
+    }
+
+    /// Splits the lines of the batch into the archives (see [`BatchOptions::archive_pattern`]),
+    /// archives are in order of their first line.
+    fn plan_archives(
+        &self,
+        archive_pattern: &str,
+        output_pattern: &str,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        context: &RowContext,
+    ) -> Vec<PlannedArchive> {
+        let mut archives: Vec<PlannedArchive> = Default::default();
+        let mut archive_of: HashMap<PathBuf, usize> = Default::default();
+        for (idx, line) in text.iter().enumerate() {
+            let (tokens, values) = self.line_pack(context, idx, tokens, line);
+            let path = self.output_path(archive_pattern, &tokens, &values);
+            let archive = *archive_of.entry(path.clone()).or_insert_with(|| {
+                archives.push(PlannedArchive {
+                    path,
+                    entries: Vec::new(),
+                });
+                archives.len() - 1
+            });
+            archives[archive].entries.push(PlannedEntry {
+                line: idx,
+                name: paths::archive_entry(output_pattern, &tokens, &values),
+                tokens,
+                values,
+            });
+        }
+        archives
+    }
+
+    /// Returns source data row number of the idx-th line of values.
+    fn source_row(&self, idx: usize) -> usize {
+        self.source_rows.get(idx).copied().unwrap_or(idx + 1)
+    }
+
+    /// Returns count of the worker threads generating batch of `total` documents.
+    fn worker_count(&self, total: usize) -> usize {
+        let workers = match self.workers {
+            0 => thread::available_parallelism().map_or(1, |count| count.get()),
+            workers => workers,
+        };
+        workers.min(total).max(1)
+    }
+
+    /// Returns full set of tokens and values of the idx-th line - line values, constants and row context.
+    fn line_pack(
+        &self,
+        context: &RowContext,
+        idx: usize,
+        tokens: TokenPackArg,
+        line: ValuePackArg,
+    ) -> (TokenPack, ValuePack) {
+        let (mut tokens, mut values) = self.constants.merge(tokens, line);
+        context.extend(idx, &mut tokens, &mut values);
+        (tokens, values)
+    }
+}
 
 trait InputOutputPiagam {}
 
@@ -53,39 +260,145 @@ pub struct DataInput {
     pub target_xml: Option<String>,
     pub header: Option<Vec<String>>,
     pub file_data: Vec<Vec<String>>,
+    /// whether the rows are not loaded, to be streamed from the file (see [`DataInput::stream_csv`]).
+    pub streamed: bool,
 }
 
 impl DataInput {
+    /// Opens the input data - CSV (`.csv`) or Excel workbook (`.xlsx`, its first sheet),
+    /// the first row is the header.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::UnsupportedData for other files, errors of reading or parsing the file.
     pub fn open<P: AsRef<Path>>(input: P) -> DocxResult<Self> {
-        if input.as_ref().ends_with(".csv") {
-            let mut rdr = csv::Reader::from_path(input.as_ref())?;
-            let header = if rdr.has_headers() {
-                Some(rdr.headers()?.deserialize(None)?)
-            } else {
-                None
-            };
-            let data = rdr
-                .deserialize()
-                .map(|f| f.unwrap())
-                .collect::<Vec<Vec<String>>>();
-
-            Ok(Self {
-                input_path: input.as_ref().to_path_buf(),
-                target_xml: None,
-                file_data: if header != None { data[1..].to_vec() } else { data },
-                header,
-            })
+        let extension = input
+            .as_ref()
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Self::open_csv(input.as_ref()),
+            Some("xlsx") => Self::open_xlsx(input.as_ref()),
+            _ => Err(DocxError::UnsupportedData {
+                path: input.as_ref().to_path_buf(),
+            }),
+        }
+    }
+
+    fn open_csv(input: &Path) -> DocxResult<Self> {
+        let mut rdr = csv::Reader::from_path(input)?;
+        let header = if rdr.has_headers() {
+            Some(rdr.headers()?.deserialize(None)?)
+        } else {
+            None
+        };
+        // the header is not among the records
+        let data = rdr
+            .deserialize()
+            .collect::<Result<Vec<Vec<String>>, csv::Error>>()?;
+
+        Ok(Self {
+            input_path: input.to_path_buf(),
+            target_xml: None,
+            file_data: data,
+            header,
+            streamed: false,
+        })
+    }
+
+    fn open_xlsx(input: &Path) -> DocxResult<Self> {
+        let content = fs::read(input)?;
+        let table = match xlsx_reader::parse_xlsx(&content, None) {
+            Ok(table) => table,
+            Err(details) => {
+                let path = input.to_path_buf();
+                return Err(DocxError::Excel { path, details });
+            }
+        };
+        let columns = table
+            .values()
+            .filter_map(|row| row.keys().max().map(|column| column + 1))
+            .max()
+            .unwrap_or(0);
+        let mut rows = (0..table.len()).map(|idx| {
+            let row = table.get(&idx);
+            (0..columns)
+                .map(|column| {
+                    row.and_then(|row| row.get(&column))
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<String>>()
+        });
+
+        Ok(Self {
+            input_path: input.to_path_buf(),
+            target_xml: None,
+            header: rows.next(),
+            file_data: rows.collect(),
+            streamed: false,
+        })
+    }
+
+    /// Opens only the header of CSV data, the rows are left in the file to be streamed (see [`DataInput::stream_csv`]).
+    ///
+    /// # Errors
+    ///
+    /// Can return errors of reading or parsing the file.
+    pub fn open_streamed<P: AsRef<Path>>(input: P) -> DocxResult<Self> {
+        let (header, _) = Self::stream_csv(input.as_ref())?;
+        Ok(Self {
+            input_path: input.as_ref().to_path_buf(),
+            target_xml: None,
+            header,
+            file_data: Vec::new(),
+            streamed: true,
+        })
+    }
+
+    /// Opens CSV data for streaming (see [`DocxTemplate::build_docx_stream`]) - returns the header,
+    /// and the data rows (line of the row in the file and its values) read from the file only as they are consumed.
+    pub fn stream_csv<P: AsRef<Path>>(
+        input: P,
+    ) -> DocxResult<(
+        Option<Vec<String>>,
+        impl Iterator<Item = DocxResult<StreamRow>>,
+    )> {
+        let mut rdr = csv::Reader::from_path(input.as_ref())?;
+        let header = if rdr.has_headers() {
+            Some(rdr.headers()?.iter().map(|h| h.to_owned()).collect())
         } else {
-            todo!("excel document not implemented yet");
-            // Ok(Self {
-            // input_path: todo!("excel document not implemented yet"),
-            // target_xml: (),
-            // file_data: (),
-            // })
+            None
+        };
+        let rows = rdr.into_records().map(|record| {
+            let record = record?;
+            let row = record
+                .position()
+                .map_or(0, |position| position.line() as usize);
+            Ok((row, record.iter().map(|v| v.to_owned()).collect()))
+        });
+        Ok((header, rows))
+    }
+
+    /// Returns number of the idx-th data row in the source file (counting the header line, if any).
+    pub fn source_row(&self, idx: usize) -> usize {
+        match self.header {
+            Some(_) => idx + 2,
+            None => idx + 1,
         }
     }
 }
 
+/// Single document rendered from the template, ready to be written into its DOCX package.
+struct RenderedDocument {
+    /// rendered document part (see [`DocxTemplate::target_xml`]).
+    document: String,
+    /// rendered other compiled parts, by their names (see [`DocxTemplate::compiled_parts`]).
+    parts: BTreeMap<String, String>,
+    /// properties filled with the values of the document.
+    properties: DocumentProperties,
+}
+
 /// Main DOCX filler / document generator.
 ///
 /// Loads the contents of DOCX template file into memory (beware huge files).
@@ -97,29 +410,76 @@ pub struct DocxTemplate {
     /// filename/path of the DOCX contents (actual text of the DOCX document).
     pub target_xml: String,
 
-    /// in-memory storage of all the DOCX contents/meta-data.
+    /// in-memory storage of the (inflated) XML contents/meta-data of the DOCX.
     pub file_data: FileMap,
+
+    /// raw content of the DOCX template file - the unchanged parts are copied from it as they are.
+    pub source: Vec<u8>,
+
+    /// parts of the DOCX package in the order they are written into the generated files.
+    pub parts: Vec<PackagePart>,
+
+    /// DOCX contents compiled for rendering (see [`DocxTemplate::target_xml`]).
+    pub document: CompiledPart,
+
+    /// other parts with tokens (headers, footers, footnotes...) compiled for rendering, by their names.
+    pub compiled_parts: BTreeMap<String, CompiledPart>,
+
+    /// whether the generated files are reproducible - the same document always yields the same bytes
+    /// (fixed timestamps and core property dates instead of the time of generation).
+    pub deterministic: bool,
+
+    /// properties (title, author, custom properties...) set in each generated document.
+    pub properties: DocumentProperties,
 }
 
 impl DocxTemplate {
     pub fn open<P: AsRef<Path>>(input: P) -> DocxResult<Self> {
         let mut file_map: FileMap = Default::default();
-        let mut zip_content = zip::ZipArchive::new(File::open(input.as_ref())?)?;
-        file_map.reserve(zip_content.len());
+        let mut parts: Vec<PackagePart> = Default::default();
+        let source = std::fs::read(input.as_ref())?;
+        let mut zip_content = zip::ZipArchive::new(Cursor::new(source.as_slice()))?;
         for i in 0..zip_content.len() {
             let mut entry = zip_content.by_index(i)?;
-            let mut file_buffer = Vec::new();
-            if let Ok(_) = entry.read_to_end(&mut file_buffer) {
-                let name = &entry.name();
-                file_map.insert(name.to_string(), file_buffer);
-            };
+            if entry.is_dir() || parts.iter().any(|part| part.name == entry.name()) {
+                continue;
+            }
+            let name = entry.name().to_string();
+            if package::is_xml_part(&name) {
+                let mut file_buffer = Vec::new();
+                entry.read_to_end(&mut file_buffer)?;
+                file_map.insert(name.clone(), file_buffer);
+            }
+            parts.push(PackagePart {
+                name,
+                index: i,
+                compression: entry.compression(),
+                last_modified: entry.last_modified(),
+            });
         }
+        package::order_parts(&mut parts);
 
-        Ok(Self {
+        let mut template = Self {
             input_path: input.as_ref().to_path_buf(),
             target_xml: "word/document.xml".to_owned(),
             file_data: file_map,
-        })
+            source,
+            parts,
+            document: Default::default(),
+            compiled_parts: Default::default(),
+            deterministic: false,
+            properties: Default::default(),
+        };
+        template.document = CompiledPart::compile(template.document_contents()?);
+        template.compiled_parts = template
+            .file_data
+            .iter()
+            .filter(|(name, _)| *name != &template.target_xml && is_compiled_part(name))
+            .filter_map(|(name, content)| Some((name, from_utf8(content).ok()?)))
+            .filter(|(_, content)| content.contains("{{"))
+            .map(|(name, content)| (name.clone(), CompiledPart::compile(content.to_owned())))
+            .collect();
+        Ok(template)
     }
 
     /// Get the tokens identified in the DOCX template.
@@ -129,24 +489,12 @@ impl DocxTemplate {
     /// Can return errors if no DOCX is loaded when attempting this,
     /// or when parsing of tokens fail.
     pub fn template_tokens(&self) -> DocxResult<TokenPack> {
-        let document = self.document_contents()?;
-
-        let re = match Regex::new(r"\{\{.*?\}\}") {
-            Ok(re) => re,
-            Err(err) => {
-                return Err(DocxError::Processing(err.to_string()));
-            }
-        };
-
-        let caps = re.captures_iter(&document);
-
         let mut tokens: TokenPack = Default::default();
-        for cap in caps {
-            if let Some(token) = cap.get(0) {
-                let token_str = token.as_str().to_owned();
-                if !tokens.contains(&token_str) {
-                    tokens.push(token_str);
-                }
+        let slots = self.compiled_parts.values().flat_map(|part| part.slots());
+        for slot in self.document.slots().chain(slots) {
+            let token_str = slot.to_owned();
+            if !tokens.contains(&token_str) && !context::is_reserved(&token_str) {
+                tokens.push(token_str);
             }
         }
 
@@ -159,13 +507,13 @@ impl DocxTemplate {
         match self.file_data.get(&self.target_xml) {
             Some(document) => match from_utf8(&document) {
                 Ok(c) => Ok(c.to_owned()),
-                Err(_) => Err(DocxError::Validation(
-                    "error on parsing utf8 string".to_owned(),
-                )),
+                Err(_) => Err(DocxError::InvalidPart {
+                    part: self.target_xml.clone(),
+                }),
             },
-            None => Err(DocxError::Processing(
-                "file input contents is empty!".to_owned(),
-            )),
+            None => Err(DocxError::MissingPart {
+                part: self.target_xml.clone(),
+            }),
         }
     }
 
@@ -193,6 +541,40 @@ impl DocxTemplate {
         Ok(())
     }
 
+    /// Renders a single DOCX from the loaded template into the writer (file, in-memory buffer,
+    /// network stream...), returns the writer back. Replaces all the tokens/placeholders
+    /// with the corresponding input values.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the DOCX package is written into
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `values` - vector of values to be filled in place of tokens
+    ///
+    /// # Errors
+    ///
+    /// Can return validation error on inconsistent tokens and values, or errors of the writer.
+    pub fn render_to<W: Write + Seek>(
+        &self,
+        writer: W,
+        tokens: TokenPackArg,
+        values: ValuePackArg,
+    ) -> DocxResult<W> {
+        validations::validate_render(tokens, values)?;
+        self.write_package(writer, &self.render(tokens, values))
+    }
+
+    /// Renders a single DOCX from the loaded template into memory (see [`DocxTemplate::render_to`]).
+    ///
+    /// # Errors
+    ///
+    /// Can return validation error on inconsistent tokens and values.
+    pub fn render_to_vec(&self, tokens: TokenPackArg, values: ValuePackArg) -> DocxResult<Vec<u8>> {
+        Ok(self
+            .render_to(Cursor::new(Vec::new()), tokens, values)?
+            .into_inner())
+    }
+
     /// Common executive method for processing one docx file generation form the loaded template.
     ///
     /// # Arguments
@@ -204,50 +586,164 @@ impl DocxTemplate {
     /// # Errors
     ///
     /// Can return errors on inconsistent input data or other internal problems (see error message for details).
+    /// Returns path of the generated file. The content is written through a temporary file,
+    /// a failure never leaves a partially written output file.
     fn data_to_docx(
         &self,
         tokens: TokenPackArg,
         values: ValuePackArg,
         output_pattern: &str,
-    ) -> DocxResult<()> {
-        let out_str = replace_tokens(output_pattern, tokens, values);
+    ) -> DocxResult<PathBuf> {
+        let out_path = paths::output_path(None, output_pattern, tokens, values);
+        let rendered = self.render(tokens, values);
+        let (path, _) =
+            self.document_to_docx(&rendered, &out_path, OverwritePolicy::Fail, |_, _| Ok(()))?;
+        Ok(path)
+    }
+
+    /// Writes DOCX file of the `rendered` document into the `out_path` (creating its folders),
+    /// existing output file is handled according to the `overwrite` policy. `created` is called once
+    /// the output file is created or before it is replaced (before its content is written), along with
+    /// whether the file was created for the document.
+    /// The content is written through a temporary file, a failure never leaves a partially written output file.
+    /// Returns path of the output file, and whether the document was generated, skipped...
+    fn document_to_docx<F>(
+        &self,
+        rendered: &RenderedDocument,
+        out_path: &Path,
+        overwrite: OverwritePolicy,
+        created: F,
+    ) -> DocxResult<(PathBuf, RowOutcome)>
+    where
+        F: FnOnce(&Path, bool) -> DocxResult<()>,
+    {
+        create_folders(out_path)?;
+        let (out_path, reserved, outcome) = match overwrite.open(out_path)? {
+            OutputFile::Created(path) => (path, true, RowOutcome::Generated),
+            OutputFile::Replaced(path) => (path, false, RowOutcome::Overwritten),
+            OutputFile::Existing(path) if overwrite == OverwritePolicy::IfChanged => {
+                let content = self
+                    .write_package(Cursor::new(Vec::new()), rendered)?
+                    .into_inner();
+                if fs::read(&path)? == content {
+                    return Ok((path, RowOutcome::Unchanged));
+                }
+                created(&path, false)?;
+                overwrite::replace_output(&path, &content)?;
+                return Ok((path, RowOutcome::Overwritten));
+            }
+            OutputFile::Existing(path) => return Ok((path, RowOutcome::Skipped)),
+        };
 
-        let out_path = PathBuf::from(&out_str);
-        if out_path.exists() {
-            let args: lang::TrArgVec = vec![("filename".to_string(), out_str)];
-            let msg = lang::tr_with_args("docx-filler-fail-overwrite", &args);
-            return Err(DocxError::Processing(msg));
+        if let Err(err) = created(&out_path, reserved) {
+            if reserved {
+                let _ = fs::remove_file(&out_path);
+            }
+            return Err(err);
         }
+        overwrite::write_output(&out_path, reserved, |file| {
+            self.write_package(file, rendered).map(|_| ())
+        })?;
+        Ok((out_path, outcome))
+    }
 
-        let zip_file = File::create(out_path)?;
-        let mut zip = zip::ZipWriter::new(zip_file);
+    /// Renders the compiled parts and the properties of a single document - tokens filled with the values.
+    fn render(&self, tokens: TokenPackArg, values: ValuePackArg) -> RenderedDocument {
+        RenderedDocument {
+            document: self.document.render(tokens, values),
+            parts: self
+                .compiled_parts
+                .iter()
+                .map(|(name, part)| (name.clone(), part.render(tokens, values)))
+                .collect(),
+            properties: self.properties.fill(tokens, values),
+        }
+    }
 
-        let options = zip::write::FileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated)
-            .unix_permissions(0o755);
+    /// Sets whether the generated files are reproducible (see [`DocxTemplate::deterministic`]).
+    pub fn with_deterministic_output(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
 
-        for (name, content) in self.file_data.iter() {
-            zip.start_file(name, options)?;
-            zip.write_all(content.as_slice())?;
-        }
+    /// Sets the properties of the generated documents (see [`DocxTemplate::properties`]).
+    pub fn with_properties(mut self, properties: DocumentProperties) -> Self {
+        self.properties = properties;
+        self
+    }
 
-        let orig_document = self.document_contents()?;
+    /// Creates the row context of a batch (see [`BatchOptions::row_context`]), generated at the fixed time
+    /// for deterministic output.
+    fn row_context(
+        &self,
+        options: &BatchOptions,
+        total: usize,
+        numbers: Vec<String>,
+    ) -> RowContext {
+        let context = options.row_context(total, numbers);
+        if self.deterministic {
+            return context.with_timestamp(properties::DETERMINISTIC_TIME);
+        }
+        context
+    }
 
-        let updated_document = replace_tokens(&orig_document, tokens, values);
-        zip.start_file(&self.target_xml, options)?;
-        zip.write_all(updated_document.as_bytes())?;
-        zip.finish()?;
+    /// Returns timestamp of the written package parts and date of the core properties -
+    /// fixed ones for deterministic output, the current time otherwise.
+    fn package_dates(&self) -> (DateTime, String) {
+        if self.deterministic {
+            let date = properties::DETERMINISTIC_DATE.to_owned();
+            return (DateTime::default(), date);
+        }
+        let now = store::now();
+        (
+            DateTime::from_time(now).unwrap_or_default(),
+            properties::w3c_date(now),
+        )
+    }
 
-        Ok(())
+    /// Writes the DOCX package of the `rendered` document into the writer, returns the writer back.
+    /// Parts are written in the template order, the compiled parts replaced by the rendered ones,
+    /// the parts changed by the filled properties and the generation dates are written after
+    /// their change (the new ones at the end), all the other parts are copied still compressed.
+    fn write_package<W: Write + Seek>(
+        &self,
+        writer: W,
+        rendered: &RenderedDocument,
+    ) -> DocxResult<W> {
+        let mut template = zip::ZipArchive::new(Cursor::new(self.source.as_slice()))?;
+        let (last_modified, date) = self.package_dates();
+        let mut changed = rendered.properties.parts(&self.file_data, &date);
+        let mut package =
+            PackageWriter::new(writer, last_modified).with_fixed_timestamps(self.deterministic);
+        for part in self.parts.iter() {
+            if part.name == self.target_xml {
+                package.write_part(part, rendered.document.as_bytes())?;
+            } else if let Some(content) = rendered.parts.get(&part.name) {
+                package.write_part(part, content.as_bytes())?;
+            } else if let Some(content) = changed.remove(&part.name) {
+                package.write_part(part, content.as_bytes())?;
+            } else {
+                package.copy_part(part, template.by_index_raw(part.index)?)?;
+            }
+        }
+        for (name, content) in changed.iter() {
+            package.add_part(name, content.as_bytes())?;
+        }
+        package.finish()
     }
 
     /// Generates batch of DOCX files form  the loaded template, one per each line of values in the input text.
+    /// Documents are generated in parallel (see [`BatchOptions::workers`]), failure of a line does not stop
+    /// generation of the other lines - failures of all the lines are reported together.
+    /// With [`BatchOptions::archive_pattern`] set, the documents are written into ZIP archives
+    /// (see [`DocxTemplate::build_docx_archives`]).
     ///
     /// # Arguments
     ///
     /// * `tokens` - vector of tokens to be replaced
     /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
     /// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+    /// * `options` - batch configuration (constants shared by all the documents, running number format...)
     ///
     /// # Errors
     ///
@@ -255,26 +751,791 @@ impl DocxTemplate {
     pub fn build_docx_batch(
         &self,
         tokens: TokenPackArg,
-        text: &Vec<ValuePack>, // TODO change into some line iterator?
+        text: &Vec<ValuePack>,
         output_pattern: &str,
+        options: &BatchOptions,
     ) -> DocxResult<()> {
-        validations::validate_batch(tokens, text, output_pattern)?;
+        if options.archive_pattern.is_some() {
+            return self
+                .build_docx_archives(tokens, text, output_pattern, options)
+                .map(|_| ());
+        }
+        let preview = self.row_context(options, text.len(), options.preview_numbers(text.len())?);
+        validations::validate_batch(tokens, text, output_pattern, options, &preview)?;
+
+        let manifest = options.open_manifest(&self.input_path, output_pattern, false)?;
+        let numbers = options.reserve_numbers(text.len())?;
+        let context = self.row_context(options, text.len(), numbers);
+        let lines: Vec<(usize, ValuePackArg)> = text
+            .iter()
+            .map(|line| line.as_slice())
+            .enumerate()
+            .collect();
+        self.generate_lines(
+            tokens,
+            &lines,
+            output_pattern,
+            options,
+            &context,
+            manifest.as_ref(),
+        )?
+        .into_result()
+    }
+
+    /// Generates batch of DOCX files in continue-on-error mode - every valid line is generated,
+    /// lines with invalid values or failing to be generated are skipped.
+    /// Returns report of the outcome of each line.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
+    /// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+    /// * `options` - batch configuration (constants shared by all the documents, running number format...)
+    ///
+    /// # Errors
+    ///
+    /// Can return error when the whole batch cannot be generated (invalid tokens, registry failures...).
+    pub fn build_docx_batch_report(
+        &self,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        output_pattern: &str,
+        options: &BatchOptions,
+    ) -> DocxResult<BatchReport> {
+        validations::validate_tokens(tokens)?;
+        let manifest = options.open_manifest(&self.input_path, output_pattern, false)?;
+        self.batch_report(
+            tokens,
+            text,
+            output_pattern,
+            options,
+            manifest.as_ref(),
+            &BTreeMap::new(),
+        )
+    }
+
+    /// Continues interrupted batch (in continue-on-error mode) recorded in the manifest
+    /// (see [`BatchOptions::manifest`]). The lines completed before, with their files still intact,
+    /// are skipped - reported as resumed, the other lines are generated.
+    /// The tokens, values and options have to be the same as for the interrupted batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
+    /// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+    /// * `options` - batch configuration, including the manifest of the interrupted batch
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::MissingManifest when no manifest is configured, or error when the whole batch
+    /// cannot be generated (invalid tokens, registry failures...).
+    pub fn resume_docx_batch(
+        &self,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        output_pattern: &str,
+        options: &BatchOptions,
+    ) -> DocxResult<BatchReport> {
+        validations::validate_tokens(tokens)?;
+        let completed = match &options.manifest {
+            Some(path) => Manifest::load_completed(path)?,
+            None => return Err(DocxError::MissingManifest),
+        };
+        let manifest = options.open_manifest(&self.input_path, output_pattern, true)?;
+        self.batch_report(
+            tokens,
+            text,
+            output_pattern,
+            options,
+            manifest.as_ref(),
+            &completed,
+        )
+    }
+
+    /// Common executive method of the continue-on-error batches - the `completed` lines
+    /// are not generated again, just recorded (registries ignore the records they have already).
+    fn batch_report(
+        &self,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        output_pattern: &str,
+        options: &BatchOptions,
+        manifest: Option<&Manifest>,
+        completed: &BTreeMap<usize, CompletedLine>,
+    ) -> DocxResult<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut fail = |idx: usize, err: DocxError| {
+            report.rows.push(RowReport {
+                line: idx,
+                row: options.source_row(idx),
+                path: None,
+                outcome: RowOutcome::Failed(err.to_string()),
+                pdf: None,
+            })
+        };
+
+        // values first, so the numbers previewed for the filenames are the ones to be reserved
+        let row_tokens = options.constants.row_tokens(tokens);
+        let mut valid: Vec<usize> = Default::default();
+        for (idx, line) in text.iter().enumerate() {
+            match validations::validate_line_values(&row_tokens, idx, line) {
+                Ok(()) if completed.contains_key(&idx) => (),
+                Ok(()) => valid.push(idx),
+                Err(err) => fail(idx, err),
+            }
+        }
+
+        // the filenames may contain the numbers - a line failing the validation shifts the numbers
+        // of the lines after it, so the remaining lines are validated again with their new numbers,
+        // until the numbers previewed are the ones reserved for exactly the valid lines
+        let completed_names: HashSet<String> = completed
+            .values()
+            .map(|done| done.path.to_string_lossy().to_lowercase())
+            .collect();
+        let mut numbers: Vec<String>;
+        loop {
+            numbers = aligned_numbers(text.len(), &valid, options.preview_numbers(valid.len())?);
+            let preview = self.row_context(options, text.len(), numbers.clone());
+            let mut names = completed_names.clone();
+            let mut failed: Vec<(usize, DocxError)> = Default::default();
+            valid.retain(|idx| {
+                let validated = validations::validate_line_filename(
+                    tokens,
+                    *idx,
+                    &text[*idx],
+                    output_pattern,
+                    options,
+                    &preview,
+                    &mut names,
+                );
+                match validated {
+                    Ok(_) => true,
+                    Err(err) => {
+                        failed.push((*idx, err));
+                        false
+                    }
+                }
+            });
+            let stable = failed.is_empty() || options.numbering.is_none();
+            for (idx, err) in failed {
+                fail(idx, err);
+            }
+            if stable {
+                break;
+            }
+        }
+
+        let lines: Vec<(usize, ValuePackArg)> = valid
+            .iter()
+            .map(|idx| (*idx, text[*idx].as_slice()))
+            .collect();
+        let reserved = aligned_numbers(text.len(), &valid, options.reserve_numbers(valid.len())?);
+        if !reserved.is_empty() || !completed.is_empty() {
+            numbers = reserved;
+            numbers.resize(text.len(), String::new());
+            for (idx, done) in completed.iter() {
+                if let (Some(number), Some(slot)) = (&done.number, numbers.get_mut(*idx)) {
+                    *slot = number.clone();
+                }
+            }
+        }
+        let context = self.row_context(options, text.len(), numbers);
+
+        let mut resumed: Vec<GeneratedDocument> = Default::default();
+        for (idx, done) in completed.iter().filter(|(idx, _)| **idx < text.len()) {
+            let (tokens, values) = options.line_pack(&context, *idx, tokens, &text[*idx]);
+            resumed.push(GeneratedDocument {
+                number: done.number.clone(),
+                tokens,
+                values,
+                path: done.path.clone(),
+                sha256: Some(done.sha256.clone()),
+            });
+            report.rows.push(RowReport {
+                line: *idx,
+                row: options.source_row(*idx),
+                path: Some(done.path.clone()),
+                outcome: RowOutcome::Resumed,
+                pdf: None,
+            });
+        }
+        let resumed_failure = record_failure(options.record(self, &resumed));
+
+        let generated =
+            self.generate_lines(tokens, &lines, output_pattern, options, &context, manifest)?;
+        report.rows.extend(generated.rows);
+        report.rows.sort_by_key(|r| r.line);
+        report.record_failure = resumed_failure.or(generated.record_failure);
+        Ok(report)
+    }
+
+    /// Generates batch of DOCX files from a stream of rows - rows are read, validated and generated
+    /// in parts, so the whole data source is never loaded into memory at once.
+    /// Total count of the documents is filled in only when the stream knows its exact length.
+    /// Output filenames are compared within each part, a line of a later part having the same output file
+    /// as an earlier one finds the file existing already (handled according to the overwrite policy).
+    /// Returns count of the rows read.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `rows` - rows (source data row number and values), a new document is generated per each of them
+    /// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+    /// * `options` - batch configuration (constants shared by all the documents, running number format...)
+    ///
+    /// # Errors
+    ///
+    /// Can return error on failure, with details in the error message. Reading or validation failure stops
+    /// the generation, the parts generated before stay generated.
+    pub fn build_docx_stream<I>(
+        &self,
+        tokens: TokenPackArg,
+        rows: I,
+        output_pattern: &str,
+        options: &BatchOptions,
+    ) -> DocxResult<usize>
+    where
+        I: IntoIterator<Item = DocxResult<StreamRow>>,
+    {
+        validations::validate_tokens(tokens)?;
+        let manifest = options.open_manifest(&self.input_path, output_pattern, false)?;
+
+        let mut rows = rows.into_iter();
+        let context = match rows.size_hint() {
+            (lower, Some(upper)) if lower == upper => self.row_context(options, lower, Vec::new()),
+            _ => self.row_context(options, 0, Vec::new()).without_total(),
+        };
+        let mut report = BatchReport::default();
+        let mut offset = 0;
+        loop {
+            let (source_rows, lines): (Vec<usize>, Vec<ValuePack>) = rows
+                .by_ref()
+                .take(STREAM_PART_ROWS)
+                .collect::<DocxResult<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+            if lines.is_empty() {
+                break;
+            }
+
+            let numbers = options.preview_numbers(lines.len())?;
+            let preview = context.part(offset, numbers, source_rows.clone());
+            validations::validate_lines(
+                tokens,
+                &lines,
+                offset,
+                output_pattern,
+                options,
+                &preview,
+                &mut HashSet::new(),
+            )?;
+
+            let part = context.part(offset, options.reserve_numbers(lines.len())?, source_rows);
+            let indexed: Vec<(usize, ValuePackArg)> = (offset..)
+                .zip(lines.iter().map(|line| line.as_slice()))
+                .collect();
+            let generated = self.generate_lines(
+                tokens,
+                &indexed,
+                output_pattern,
+                options,
+                &part,
+                manifest.as_ref(),
+            )?;
+            report
+                .rows
+                .extend(generated.rows.into_iter().filter(|r| !r.is_generated()));
+            report.record_failure = report.record_failure.or(generated.record_failure);
+            offset += lines.len();
+        }
+
+        if offset == 0 {
+            return Err(DocxError::MissingInput);
+        }
+        report.into_result().map(|_| offset)
+    }
+
+    /// Generates the batch merged into a single DOCX file for printing, or into one file per each group
+    /// of the lines (see [`MergeOptions::group_token`]). Documents of the lines follow each other in order,
+    /// separated by section or page breaks. Headers, footers and properties are in the merged file only once,
+    /// so all the lines of a group have to fill them the same way. Returns paths of the merged files.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `text` - one to many lines of text - a set of values per each line of the merged document
+    /// * `output_pattern` - output file pattern, filled with the values of the first line of each group
+    /// * `options` - batch configuration (constants shared by all the documents, running number format...)
+    /// * `merge` - how the documents are merged
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::ManifestUnsupported when the manifest is configured, Docx::Line with
+    /// Docx::MergedPartDiffers when a header, footer or property would differ within a group,
+    /// or error on other failure, with details in the error message.
+    pub fn build_docx_merged(
+        &self,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        output_pattern: &str,
+        options: &BatchOptions,
+        merge: &MergeOptions,
+    ) -> DocxResult<Vec<PathBuf>> {
+        if options.manifest.is_some() {
+            return Err(DocxError::ManifestUnsupported);
+        }
+        validations::validate_tokens(tokens)?;
+        if text.is_empty() {
+            return Err(DocxError::MissingInput);
+        }
+        let row_tokens = options.constants.row_tokens(tokens);
+        for (idx, line) in text.iter().enumerate() {
+            validations::validate_line_values(&row_tokens, idx, line)?;
+        }
 
-        for line in text {
-            self.data_to_docx(tokens, &line, output_pattern)?;
+        let preview = self.row_context(options, text.len(), options.preview_numbers(text.len())?);
+        let preview_packs: Vec<(TokenPack, ValuePack)> = text
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| options.line_pack(&preview, idx, tokens, line))
+            .collect();
+        let groups = merge.groups(&preview_packs)?;
+        let mut names: HashSet<String> = Default::default();
+        for group in groups.iter() {
+            self.validate_merged_group(group, &preview_packs)?;
+            let idx = group[0];
+            validations::validate_line_filename(
+                tokens,
+                idx,
+                &text[idx],
+                output_pattern,
+                options,
+                &preview,
+                &mut names,
+            )?;
         }
 
+        let context = self.row_context(options, text.len(), options.reserve_numbers(text.len())?);
+        let packs: Vec<(TokenPack, ValuePack)> = text
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| options.line_pack(&context, idx, tokens, line))
+            .collect();
+        let workers = options.worker_count(groups.len());
+        let results = parallel_map(groups.len(), workers, |g| -> DocxResult<_> {
+            let group = &groups[g];
+            let mut merger = DocumentMerger::new(merge.separator);
+            for idx in group.iter() {
+                let (tokens, values) = &packs[*idx];
+                merger.push(&self.document.render(tokens, values))?;
+            }
+            // headers, footers and properties are the same for all the documents of the group
+            let (tokens, values) = &packs[group[0]];
+            let mut rendered = self.render(tokens, values);
+            rendered.document = merger.finish()?;
+            let out_path = options.output_path(output_pattern, tokens, values);
+            self.document_to_docx(&rendered, &out_path, options.overwrite, |_, _| Ok(()))
+        });
+
+        let mut merged: Vec<PathBuf> = Default::default();
+        let mut generated: Vec<GeneratedDocument> = Default::default();
+        for (group, result) in groups.iter().zip(results) {
+            let (path, outcome) = result?;
+            if outcome != RowOutcome::Skipped {
+                generated.extend(group.iter().map(|idx| GeneratedDocument {
+                    number: context.number(*idx).map(|n| n.to_owned()),
+                    tokens: packs[*idx].0.clone(),
+                    values: packs[*idx].1.clone(),
+                    path: path.clone(),
+                    sha256: None,
+                }));
+            }
+            merged.push(path);
+        }
+        match record_failure(options.record(self, &generated)) {
+            Some(details) => Err(DocxError::NotRecorded { details }),
+            None => Ok(merged),
+        }
+    }
+
+    /// Checks the lines of the merged group fill the parts other than the document (headers, footers...)
+    /// and the properties the same way - the merged document has them only once, filled by its first line.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::Line with Docx::MergedPartDiffers for the first line filling them differently.
+    fn validate_merged_group(
+        &self,
+        group: &[usize],
+        packs: &[(TokenPack, ValuePack)],
+    ) -> DocxResult<()> {
+        let (tokens, values) = &packs[group[0]];
+        let parts: Vec<(&String, &CompiledPart, String)> = self
+            .compiled_parts
+            .iter()
+            .map(|(name, part)| (name, part, part.render(tokens, values)))
+            .collect();
+        let properties = self.properties.fill(tokens, values);
+        for idx in group.iter().skip(1) {
+            let (tokens, values) = &packs[*idx];
+            let differing = parts
+                .iter()
+                .find(|(_, part, content)| part.render(tokens, values) != *content)
+                .map(|(name, _, _)| name.to_string())
+                .or_else(|| {
+                    let filled = self.properties.fill(tokens, values);
+                    properties.differing_part(&filled).map(str::to_owned)
+                });
+            if let Some(part) = differing {
+                return Err(DocxError::Line {
+                    line: idx + 1,
+                    source: Box::new(DocxError::MergedPartDiffers { part }),
+                });
+            }
+        }
         Ok(())
     }
+
+    /// Generates batch of DOCX files written straight into ZIP archives (see [`BatchOptions::archive_pattern`]),
+    /// each archive holds also index of its documents ([`archive::INDEX_ENTRY`]).
+    /// Archives are generated in parallel. Returns paths of the archives.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - vector of tokens to be replaced
+    /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
+    /// * `output_pattern` - pattern of the document path within its archive
+    /// * `options` - batch configuration, including the archive pattern
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::ManifestUnsupported when the manifest is configured, or error on other failure,
+    /// with details in the error message. Documents of the archives written despite failure of other
+    /// archives are recorded.
+    pub fn build_docx_archives(
+        &self,
+        tokens: TokenPackArg,
+        text: &[ValuePack],
+        output_pattern: &str,
+        options: &BatchOptions,
+    ) -> DocxResult<Vec<PathBuf>> {
+        let archive_pattern = match &options.archive_pattern {
+            Some(pattern) => pattern,
+            None => return Err(DocxError::MissingArchivePattern),
+        };
+        if options.manifest.is_some() {
+            return Err(DocxError::ManifestUnsupported);
+        }
+        validations::validate_tokens(tokens)?;
+        if text.is_empty() {
+            return Err(DocxError::MissingInput);
+        }
+        let row_tokens = options.constants.row_tokens(tokens);
+        for (idx, line) in text.iter().enumerate() {
+            validations::validate_line_values(&row_tokens, idx, line)?;
+        }
+        let preview = self.row_context(options, text.len(), options.preview_numbers(text.len())?);
+        let planned =
+            options.plan_archives(archive_pattern, output_pattern, tokens, text, &preview);
+        validations::validate_archives(&planned, options)?;
+
+        let context = self.row_context(options, text.len(), options.reserve_numbers(text.len())?);
+        let archives =
+            options.plan_archives(archive_pattern, output_pattern, tokens, text, &context);
+        let workers = options.worker_count(archives.len());
+        let results = parallel_map(archives.len(), workers, |a| {
+            self.archive_to_zip(&archives[a], options, &context)
+        });
+
+        let mut paths: Vec<PathBuf> = Default::default();
+        let mut generated: Vec<GeneratedDocument> = Default::default();
+        let mut failure: Option<DocxError> = None;
+        for result in results {
+            match result {
+                Ok((path, documents)) => {
+                    paths.push(path);
+                    generated.extend(documents);
+                }
+                Err(err) => failure = failure.or(Some(err)),
+            }
+        }
+        let recorded = record_failure(options.record(self, &generated));
+        match (failure, recorded) {
+            (Some(err), _) => Err(err),
+            (None, Some(details)) => Err(DocxError::NotRecorded { details }),
+            (None, None) => Ok(paths),
+        }
+    }
+
+    /// Writes the planned archive (creating its folders), existing archive is handled according to
+    /// the overwrite policy. Returns path of the archive and the documents written into it.
+    fn archive_to_zip(
+        &self,
+        archive: &PlannedArchive,
+        options: &BatchOptions,
+        context: &RowContext,
+    ) -> DocxResult<(PathBuf, Vec<GeneratedDocument>)> {
+        create_folders(&archive.path)?;
+        let (path, reserved) = match options.overwrite.open(&archive.path)? {
+            OutputFile::Created(path) => (path, true),
+            OutputFile::Replaced(path) => (path, false),
+            OutputFile::Existing(path) if options.overwrite == OverwritePolicy::IfChanged => {
+                let (content, documents) =
+                    self.write_archive(Cursor::new(Vec::new()), &path, archive, options, context)?;
+                let content = content.into_inner();
+                if fs::read(&path)? != content {
+                    overwrite::replace_output(&path, &content)?;
+                }
+                return Ok((path, documents));
+            }
+            OutputFile::Existing(path) => return Ok((path, Vec::new())),
+        };
+        let documents = overwrite::write_output(&path, reserved, |file| {
+            let (_, documents) = self.write_archive(file, &path, archive, options, context)?;
+            Ok(documents)
+        })?;
+        Ok((path, documents))
+    }
+
+    /// Writes the documents of the planned archive, along with their index, into the writer.
+    /// Returns the writer back and the documents written (`path` is the archive path).
+    fn write_archive<W: Write + Seek>(
+        &self,
+        writer: W,
+        path: &Path,
+        archive: &PlannedArchive,
+        options: &BatchOptions,
+        context: &RowContext,
+    ) -> DocxResult<(W, Vec<GeneratedDocument>)> {
+        let (last_modified, _) = self.package_dates();
+        // the reserved tokens are not listed, number has its own column
+        let tokens = archive
+            .entries
+            .first()
+            .map(|entry| entry.tokens.as_slice())
+            .unwrap_or_default();
+        let columns: Vec<usize> = (0..tokens.len())
+            .filter(|i| !context::is_reserved(&tokens[*i]))
+            .collect();
+        let header: TokenPack = columns.iter().map(|i| tokens[*i].clone()).collect();
+
+        let mut writer = ArchiveWriter::new(writer, &header, last_modified)?;
+        let mut documents: Vec<GeneratedDocument> = Default::default();
+        for entry in archive.entries.iter() {
+            let rendered = self.render(&entry.tokens, &entry.values);
+            let content = self
+                .write_package(Cursor::new(Vec::new()), &rendered)?
+                .into_inner();
+            let number = context.number(entry.line);
+            let values: ValuePack = columns.iter().map(|i| entry.values[*i].clone()).collect();
+            let row = options.source_row(entry.line);
+            writer.add(&entry.name, row, number, &values, &content)?;
+            documents.push(GeneratedDocument {
+                number: number.map(|n| n.to_owned()),
+                tokens: entry.tokens.clone(),
+                values: entry.values.clone(),
+                path: path.join(&entry.name),
+                sha256: Some(store::sha256(&content)),
+            });
+        }
+        Ok((writer.finish()?, documents))
+    }
+
+    /// Generates documents of the lines (index of the line in the batch and its values) in parallel,
+    /// records the generated ones (and the progress into the manifest). Returns outcome of each of the lines,
+    /// and failure of the recording - it does not change the outcome of the lines generated.
+    fn generate_lines(
+        &self,
+        tokens: TokenPackArg,
+        lines: &[(usize, ValuePackArg)],
+        output_pattern: &str,
+        options: &BatchOptions,
+        context: &RowContext,
+        manifest: Option<&Manifest>,
+    ) -> DocxResult<BatchReport> {
+        let packs: Vec<(TokenPack, ValuePack)> = lines
+            .iter()
+            .map(|(idx, line)| options.line_pack(context, *idx, tokens, line))
+            .collect();
+        let filenames: Vec<PathBuf> = packs
+            .iter()
+            .map(|(tokens, values)| options.output_path(output_pattern, tokens, values))
+            .collect();
+        if let Some(manifest) = manifest {
+            for ((idx, _), filename) in lines.iter().zip(filenames.iter()) {
+                manifest.planned(*idx, context.source_row(*idx), filename)?;
+            }
+        }
+
+        let workers = options.worker_count(lines.len());
+        let results = parallel_map(lines.len(), workers, |i| -> DocxResult<_> {
+            let idx = lines[i].0;
+            let (tokens, values) = &packs[i];
+            let number = context.number(idx);
+            let (path, outcome) = self.document_to_docx(
+                &self.render(tokens, values),
+                &filenames[i],
+                options.overwrite,
+                |path, created| match manifest {
+                    Some(manifest) => manifest.started(idx, path, created),
+                    None => Ok(()),
+                },
+            )?;
+            match (manifest, &outcome) {
+                (Some(manifest), RowOutcome::Skipped) => manifest.skipped(idx, &path)?,
+                (Some(manifest), _) => manifest.completed(idx, &path, number)?,
+                (None, _) => (),
+            }
+            let document = GeneratedDocument {
+                number: number.map(|n| n.to_owned()),
+                tokens: tokens.clone(),
+                values: values.clone(),
+                path,
+                sha256: None,
+            };
+            Ok((document, outcome))
+        });
+
+        let mut generated: Vec<GeneratedDocument> = Default::default();
+        let mut reports: Vec<RowReport> = Default::default();
+        for (((idx, _), filename), result) in lines.iter().zip(filenames).zip(results) {
+            let (path, outcome) = match result {
+                Ok((document, RowOutcome::Skipped)) => (document.path, RowOutcome::Skipped),
+                Ok((document, outcome)) => {
+                    let path = document.path.clone();
+                    generated.push(document);
+                    (path, outcome)
+                }
+                Err(err) => (filename, RowOutcome::Failed(err.to_string())),
+            };
+            reports.push(RowReport {
+                line: *idx,
+                row: context.source_row(*idx),
+                path: Some(path),
+                outcome,
+                pdf: None,
+            });
+        }
+        if let Some(converter) = &options.converter {
+            convert_reports(converter.as_ref(), &mut reports);
+        }
+
+        // documents generated despite failures of other lines are recorded as well
+        Ok(BatchReport {
+            rows: reports,
+            record_failure: record_failure(options.record(self, &generated)),
+        })
+    }
+}
+
+/// Creates the folders of the output file, if missing.
+fn create_folders(out_path: &Path) -> DocxResult<()> {
+    match out_path.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => Ok(fs::create_dir_all(folder)?),
+        _ => Ok(()),
+    }
+}
+
+/// Returns reason the generated documents could not be recorded into the registries, if they were not.
+fn record_failure(recorded: DocxResult<()>) -> Option<String> {
+    recorded.err().map(|err| err.to_string())
+}
+
+/// Spreads the numbers assigned to the `lines` over all the `total` lines of a batch
+/// (the other lines get empty number).
+fn aligned_numbers(total: usize, lines: &[usize], numbers: Vec<String>) -> Vec<String> {
+    if numbers.is_empty() {
+        return numbers;
+    }
+    let mut aligned = vec![String::new(); total];
+    for (idx, number) in lines.iter().zip(numbers) {
+        aligned[*idx] = number;
+    }
+    aligned
+}
+
+/// Whether tokens of the part are filled - content parts of the document (headers, footers,
+/// footnotes...), the properties are filled by [`DocumentProperties`].
+fn is_compiled_part(name: &str) -> bool {
+    name.starts_with("word/") && name.ends_with(".xml")
+}
+
+/// Calls the function for each index in `0..count` on `workers` threads, returns the results in order of the indexes.
+fn parallel_map<T, F>(count: usize, workers: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done: Vec<(usize, T)> = Default::default();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        if idx >= count {
+                            return done;
+                        }
+                        done.push((idx, f(idx)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(done) => done,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Converts output files of the generated lines into PDF, the result is set into the report of each line.
+fn convert_reports(converter: &dyn Converter, reports: &mut [RowReport]) {
+    let mut converted: Vec<&mut RowReport> = reports
+        .iter_mut()
+        .filter(|report| report.is_generated() && report.path.is_some())
+        .collect();
+    let files: Vec<PathBuf> = converted
+        .iter()
+        .filter_map(|report| report.path.clone())
+        .collect();
+    for (report, result) in converted.iter_mut().zip(converter.convert(&files)) {
+        report.pdf = Some(result.map_err(|err| err.to_string()));
+    }
 }
 
 /// Fill in the input string with specified set of tokens and values.
+/// All the tokens are replaced at once in a single pass - the longest token matching at a position wins,
+/// and the filled values are never searched for tokens again. Tokens without a value are kept as they are
+/// (counts of the tokens and values are validated before the documents are rendered).
 fn replace_tokens(input: &str, tokens: TokenPackArg, values: ValuePackArg) -> String {
-    assert_eq!(tokens.len(), values.len());
-    let mut output: String = input.to_string();
-    tokens.iter().enumerate().for_each(|(idx, token)| {
-        output = output.replace(token, &values[idx]);
-    });
+    let tokens = &tokens[..tokens.len().min(values.len())];
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(ch) = rest.chars().next() {
+        let longest = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| !token.is_empty() && rest.starts_with(token.as_str()))
+            .max_by_key(|(_, token)| token.len());
+        match longest {
+            Some((idx, token)) => {
+                output.push_str(&values[idx]);
+                rest = &rest[token.len()..];
+            }
+            None => {
+                output.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
     output
 }
 
@@ -294,3 +1555,850 @@ fn vec_str_to_values(input: &Vec<String>) -> ValuePack {
         .map(|x| x.trim().to_owned())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use registry::CertificateRegistry;
+    use testing::{paragraph, strings, write_template, TempDir};
+
+    /// Opens template with the body content, written into the folder.
+    fn template(dir: &TempDir, body: &str) -> DocxTemplate {
+        let path = dir.join("piagam.docx");
+        write_template(&path, body, &[]);
+        DocxTemplate::open(&path).unwrap()
+    }
+
+    fn lines(values: &[&str]) -> Vec<ValuePack> {
+        values.iter().map(|value| strings(&[value])).collect()
+    }
+
+    #[test]
+    fn reissue_generates_with_the_settings_of_the_batch() {
+        let dir = TempDir::new("batch-reissue");
+        let template = template(&dir, &paragraph("{{nama}} {{tahun}}"))
+            .with_deterministic_output(true)
+            .with_properties(DocumentProperties {
+                title: Some("Piagam {{nama}} {{tahun}}".to_owned()),
+                ..Default::default()
+            });
+        let mut options = BatchOptions {
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(dir.join("registry.json")),
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        options.constants.insert("{{tahun}}", "2024");
+        let tokens = strings(&["{{nama}}"]);
+        let pattern = dir.pattern("Budi.docx");
+        template
+            .build_docx_batch(&tokens, &lines(&["Budi"]), &pattern, &options)
+            .unwrap();
+
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let original = registry.certificates().unwrap().remove(0);
+        let corrections = vec![("{{nama}}".to_owned(), "Budi Santoso".to_owned())];
+        let reissued = registry.reissue(&original.sha256, &corrections).unwrap();
+
+        assert_eq!(reissued.generation, original.generation);
+        let document = testing::read_part(&reissued.file, "word/document.xml");
+        assert!(document.contains("<w:t>Budi Santoso 2024</w:t>"));
+        let core = testing::read_part(&reissued.file, "docProps/core.xml");
+        assert!(core.contains("<dc:title>Piagam Budi Santoso 2024</dc:title>"));
+        assert!(core.contains(properties::DETERMINISTIC_DATE));
+    }
+
+    /// Returns the compressed (raw) content and CRC of the package entry.
+    fn raw_entry(docx: &[u8], name: &str) -> (Vec<u8>, u32) {
+        let mut zip = zip::ZipArchive::new(Cursor::new(docx)).unwrap();
+        let index = (0..zip.len())
+            .find(|i| zip.by_index_raw(*i).unwrap().name() == name)
+            .unwrap();
+        let mut entry = zip.by_index_raw(index).unwrap();
+        let mut raw = Vec::new();
+        entry.read_to_end(&mut raw).unwrap();
+        (raw, entry.crc32())
+    }
+
+    #[test]
+    fn unchanged_parts_are_copied_without_recompression() {
+        let dir = TempDir::new("raw-copy");
+        let path = dir.join("piagam.docx");
+        let styles = "<w:styles>".to_owned() + &"<w:style/>".repeat(100) + "</w:styles>";
+        write_template(
+            &path,
+            &paragraph("{{nama}}"),
+            &[("word/styles.xml", &styles)],
+        );
+        let template = DocxTemplate::open(&path).unwrap();
+
+        let docx = template
+            .render_to_vec(&strings(&["{{nama}}"]), &strings(&["Budi"]))
+            .unwrap();
+
+        let source = fs::read(&path).unwrap();
+        assert_eq!(
+            raw_entry(&docx, "word/styles.xml"),
+            raw_entry(&source, "word/styles.xml")
+        );
+        assert_ne!(
+            raw_entry(&docx, "word/document.xml"),
+            raw_entry(&source, "word/document.xml")
+        );
+        assert_eq!(testing::read_part_bytes(&docx, "word/styles.xml"), styles);
+    }
+
+    #[test]
+    fn headers_and_footers_are_filled() {
+        let dir = TempDir::new("compiled-parts");
+        let path = dir.join("piagam.docx");
+        let header = "<w:hdr><w:p><w:r><w:t>{{sekolah}}</w:t></w:r></w:p></w:hdr>";
+        let footer = "<w:ftr><w:p><w:r><w:t>{{_nomor}}</w:t></w:r></w:p></w:ftr>";
+        write_template(
+            &path,
+            &paragraph("{{nama}}"),
+            &[("word/header1.xml", header), ("word/footer1.xml", footer)],
+        );
+        let template = DocxTemplate::open(&path).unwrap();
+        assert_eq!(
+            template.template_tokens().unwrap(),
+            strings(&["{{nama}}", "{{sekolah}}"])
+        );
+
+        let docx = template
+            .render_to_vec(
+                &strings(&["{{nama}}", "{{sekolah}}", context::TOKEN_NUMBER]),
+                &strings(&["Budi", "SDN 1", "421/001"]),
+            )
+            .unwrap();
+        let part = |name: &str| testing::read_part_bytes(&docx, name);
+        assert!(part("word/document.xml").contains("<w:t>Budi</w:t>"));
+        assert_eq!(
+            part("word/header1.xml"),
+            header.replace("{{sekolah}}", "SDN 1")
+        );
+        assert_eq!(
+            part("word/footer1.xml"),
+            footer.replace("{{_nomor}}", "421/001")
+        );
+    }
+
+    #[test]
+    fn replace_tokens_prefers_longest_token() {
+        let tokens = strings(&["{{nama}}", "{{nama}}_lengkap", "{{n"]);
+        let values = strings(&["Budi", "Budi Santoso", "X"]);
+        assert_eq!(
+            replace_tokens("{{nama}}_lengkap, {{nama}}, {{nomor}}", &tokens, &values),
+            "Budi Santoso, Budi, Xomor}}"
+        );
+    }
+
+    #[test]
+    fn replace_tokens_does_not_expand_tokens_in_values() {
+        let tokens = strings(&["{{a}}", "{{b}}"]);
+        let values = strings(&["{{b}}", "{{a}}"]);
+        assert_eq!(
+            replace_tokens("{{a}}-{{b}}", &tokens, &values),
+            "{{b}}-{{a}}"
+        );
+    }
+
+    #[test]
+    fn replace_tokens_keeps_tokens_without_values() {
+        let tokens = strings(&["{{a}}", "", "{{č}}"]);
+        let values = strings(&["1", "2"]);
+        assert_eq!(
+            replace_tokens("žluť {{a}}{{č}}", &tokens, &values),
+            "žluť 1{{č}}"
+        );
+        assert_eq!(replace_tokens("", &tokens, &values), "");
+        let values = strings(&["1", "2", "3", "4"]);
+        assert_eq!(replace_tokens("{{a}}{{č}}", &tokens, &values), "13");
+    }
+
+    #[test]
+    fn parallel_batch_generates_every_line_in_order() {
+        let dir = TempDir::new("parallel-batch");
+        let template = template(&dir, &paragraph("{{nama}} {{_urut}}"));
+        let names: Vec<String> = (1..=20).map(|i| format!("Siswa {}", i)).collect();
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let options = BatchOptions {
+            workers: 4,
+            ..Default::default()
+        };
+
+        let report = template
+            .build_docx_batch_report(
+                &strings(&["{{nama}}"]),
+                &lines(&names),
+                &dir.pattern("{{nama}}.docx"),
+                &options,
+            )
+            .unwrap();
+
+        assert!(!report.has_failures());
+        let lines: Vec<usize> = report.rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, (0..20).collect::<Vec<usize>>());
+        let document = testing::read_part(&dir.join("Siswa 17.docx"), "word/document.xml");
+        assert!(document.contains("<w:t>Siswa 17 17</w:t>"));
+    }
+
+    #[test]
+    fn stream_csv_gives_source_lines_of_the_rows() {
+        let dir = TempDir::new("stream-csv");
+        let data = dir.join("data.csv");
+        fs::write(&data, "nama,kelas\nBudi,6\n\"Ani\nSari\",5\nCitra,4\n").unwrap();
+
+        let (header, rows) = DataInput::stream_csv(&data).unwrap();
+        let rows: Vec<StreamRow> = rows.collect::<DocxResult<_>>().unwrap();
+
+        assert_eq!(header, Some(strings(&["nama", "kelas"])));
+        assert_eq!(
+            rows,
+            vec![
+                (2, strings(&["Budi", "6"])),
+                (3, strings(&["Ani\nSari", "5"])),
+                (5, strings(&["Citra", "4"])),
+            ]
+        );
+        let streamed = DataInput::open_streamed(&data).unwrap();
+        assert!(streamed.streamed && streamed.file_data.is_empty());
+        assert_eq!(streamed.header, header);
+    }
+
+    /// Writes Excel workbook with the shared strings and the first sheet.
+    fn write_xlsx(path: &Path, strings: &str, sheet: &str) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in [
+            ("xl/sharedStrings.xml", strings),
+            ("xl/worksheets/sheet1.xml", sheet),
+        ] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn open_reads_csv_and_xlsx_data() {
+        let dir = TempDir::new("data-input");
+        let csv = dir.join("data.CSV");
+        fs::write(&csv, "nama,kelas\nBudi,6\nAni,5\n").unwrap();
+
+        let data = DataInput::open(&csv).unwrap();
+        assert_eq!(data.header, Some(strings(&["nama", "kelas"])));
+        assert_eq!(
+            data.file_data,
+            vec![strings(&["Budi", "6"]), strings(&["Ani", "5"])]
+        );
+        assert!(!data.streamed);
+
+        let xlsx = dir.join("data.xlsx");
+        let shared = "<sst><si><t>nama</t></si><si><t>kelas</t></si><si><t>Budi</t></si></sst>";
+        // the second row misses its first cell, the third one its last
+        let sheet = "<worksheet><sheetData>\
+            <row><c r=\"A1\" t=\"s\"><v>0</v></c><c r=\"B1\" t=\"s\"><v>1</v></c></row>\
+            <row><c r=\"B2\"><v>6</v></c></row>\
+            <row><c r=\"A3\" t=\"s\"><v>2</v></c></row>\
+            </sheetData></worksheet>";
+        write_xlsx(&xlsx, shared, sheet);
+
+        let data = DataInput::open(&xlsx).unwrap();
+        assert_eq!(data.header, Some(strings(&["nama", "kelas"])));
+        assert_eq!(
+            data.file_data,
+            vec![strings(&["", "6"]), strings(&["Budi", ""])]
+        );
+    }
+
+    #[test]
+    fn open_reports_unsupported_and_malformed_data() {
+        let dir = TempDir::new("data-input-invalid");
+        let text = dir.join("data.txt");
+        fs::write(&text, "nama\nBudi\n").unwrap();
+        assert!(matches!(
+            DataInput::open(&text),
+            Err(DocxError::UnsupportedData { path }) if path == text
+        ));
+        assert!(matches!(
+            DataInput::open(dir.join("data")),
+            Err(DocxError::UnsupportedData { .. })
+        ));
+
+        let csv = dir.join("data.csv");
+        fs::write(&csv, "nama,kelas\nBudi\n").unwrap();
+        assert!(matches!(DataInput::open(&csv), Err(DocxError::CsvError(_))));
+        assert!(matches!(
+            DataInput::open(dir.join("missing.csv")),
+            Err(DocxError::CsvError(_))
+        ));
+
+        let garbage = dir.join("garbage.xlsx");
+        fs::write(&garbage, "not a workbook").unwrap();
+        assert!(matches!(
+            DataInput::open(&garbage),
+            Err(DocxError::Excel { path, .. }) if path == garbage
+        ));
+        let empty = dir.join("empty.xlsx");
+        write_xlsx(&empty, "<sst></sst>", "<worksheet></worksheet>");
+        assert!(matches!(
+            DataInput::open(&empty),
+            Err(DocxError::Excel { .. })
+        ));
+        assert!(matches!(
+            DataInput::open(dir.join("missing.xlsx")),
+            Err(DocxError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn stream_fills_source_rows_and_finds_outputs_of_earlier_parts() {
+        let dir = TempDir::new("stream-rows");
+        let template = template(&dir, &paragraph("{{nama}}/{{_baris}}"));
+        let pattern = dir.pattern("{{nama}}.docx");
+        let rows = (0..STREAM_PART_ROWS).map(|i| Ok((10 + i, strings(&[&format!("S{}", i)]))));
+        // the same output as the first row, in the next part
+        let rows = rows.chain([Ok((999, strings(&["S0"])))]);
+
+        let err = template
+            .build_docx_stream(&strings(&["{{nama}}"]), rows, &pattern, &Default::default())
+            .unwrap_err();
+
+        assert!(matches!(err, DocxError::OutputExists { path } if path == dir.join("S0.docx")));
+        let document = testing::read_part(&dir.join("S255.docx"), "word/document.xml");
+        assert!(document.contains("<w:t>S255/265</w:t>"));
+    }
+
+    #[test]
+    fn registry_failure_does_not_replace_generated_rows() {
+        let dir = TempDir::new("batch-not-recorded");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        // registry which cannot be read
+        fs::create_dir_all(dir.join("registry.json")).unwrap();
+        let options = BatchOptions {
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(dir.join("registry.json")),
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        let tokens = strings(&["{{nama}}"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+
+        let report = template
+            .build_docx_batch_report(&tokens, &lines(&["Budi", "Ani"]), &pattern, &options)
+            .unwrap();
+
+        assert_eq!(report.generated().count(), 2);
+        assert!(report.record_failure.is_some());
+        assert!(report.has_failures());
+        assert!(dir.join("Ani.docx").exists());
+        let err = report.into_result().unwrap_err();
+        assert!(matches!(err, DocxError::NotRecorded { .. }));
+    }
+
+    #[test]
+    fn numbers_are_aligned_to_their_lines() {
+        let numbers = aligned_numbers(4, &[0, 2, 3], strings(&["1", "2", "3"]));
+        assert_eq!(numbers, strings(&["1", "", "2", "3"]));
+        assert!(aligned_numbers(4, &[0, 2], Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn line_failing_filename_does_not_take_a_number() {
+        let dir = TempDir::new("batch-numbers");
+        let template = template(&dir, &paragraph("{{nama}} {{_nomor}}"));
+        let format = numbering::SerialFormat {
+            series: "piagam".to_owned(),
+            prefix: String::new(),
+            office: String::new(),
+            padding: 3,
+            pattern: "{seq}".to_owned(),
+            yearly: false,
+        };
+        let options = BatchOptions {
+            numbering: Some(Numbering {
+                registry: numbering::NumberRegistry::new(dir.join("nomor.json")),
+                format: format.clone(),
+            }),
+            ..Default::default()
+        };
+        // the output file of the second line exists, only with the number previewed first
+        fs::write(dir.join("Ani-002.docx"), b"kept").unwrap();
+        let tokens = strings(&["{{nama}}"]);
+        let pattern = dir.pattern("{{nama}}-{{_nomor}}.docx");
+
+        let lines = lines(&["Budi", "Ani", "Citra"]);
+
+        let report = template
+            .build_docx_batch_report(&tokens, &lines, &pattern, &options)
+            .unwrap();
+
+        let generated: Vec<_> = report.generated().filter_map(|r| r.path.clone()).collect();
+        let expected = vec![dir.join("Budi-001.docx"), dir.join("Citra-002.docx")];
+        assert_eq!(generated, expected);
+        assert_eq!(report.failed().map(|r| r.line).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(fs::read(dir.join("Ani-002.docx")).unwrap(), b"kept");
+        let document = testing::read_part(&dir.join("Citra-002.docx"), "word/document.xml");
+        assert!(document.contains("Citra 002"));
+        let registry = numbering::NumberRegistry::new(dir.join("nomor.json"));
+        assert_eq!(registry.preview(&format, 1).unwrap(), strings(&["003"]));
+    }
+
+    #[test]
+    fn resumed_batch_records_each_line_once() {
+        let dir = TempDir::new("batch-resume-registry");
+        let template = template(&dir, &paragraph("{{nama}} {{_waktu}}"));
+        let options = BatchOptions {
+            manifest: Some(dir.join("piagam.manifest.jsonl")),
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(dir.join("registry.json")),
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        let tokens = strings(&["{{nama}}"]);
+        let lines = lines(&["Budi", "Ani", "Citra"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+        // the last line fails, the first two are generated and recorded already when resumed
+        fs::write(dir.join("Citra.docx"), b"blocking").unwrap();
+        let report = template
+            .build_docx_batch_report(&tokens, &lines, &pattern, &options)
+            .unwrap();
+        assert_eq!(report.failed().count(), 1);
+        fs::remove_file(dir.join("Citra.docx")).unwrap();
+
+        let report = template
+            .resume_docx_batch(&tokens, &lines, &pattern, &options)
+            .unwrap();
+
+        let outcomes: Vec<_> = report.rows.iter().map(|r| r.outcome.clone()).collect();
+        let resumed = RowOutcome::Resumed;
+        assert_eq!(outcomes, [resumed.clone(), resumed, RowOutcome::Generated]);
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let mut files: Vec<_> = registry
+            .certificates()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.file)
+            .collect();
+        files.sort();
+        let expected = ["Ani.docx", "Budi.docx", "Citra.docx"].map(|name| dir.join(name));
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn existing_outputs_are_kept_replaced_or_suffixed() {
+        let dir = TempDir::new("batch-overwrite");
+        let template =
+            template(&dir, &paragraph("{{nama}} {{kelas}}")).with_deterministic_output(true);
+        let tokens = strings(&["{{nama}}", "{{kelas}}"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+        let batch = |lines: &[ValuePack], overwrite: OverwritePolicy| {
+            let options = BatchOptions {
+                overwrite,
+                ..Default::default()
+            };
+            let report = template
+                .build_docx_batch_report(&tokens, lines, &pattern, &options)
+                .unwrap();
+            report
+                .rows
+                .into_iter()
+                .map(|r| (r.path.unwrap(), r.outcome))
+                .collect::<Vec<_>>()
+        };
+        let budi = strings(&["Budi", "6A"]);
+        let lines = [budi.clone(), strings(&["Ani", "6A"])];
+        batch(&lines, OverwritePolicy::Fail);
+
+        let lines = [budi.clone(), strings(&["Ani", "6B"])];
+        let outcomes = batch(&lines, OverwritePolicy::IfChanged);
+        let expected = [
+            (dir.join("Budi.docx"), RowOutcome::Unchanged),
+            (dir.join("Ani.docx"), RowOutcome::Overwritten),
+        ];
+        assert_eq!(outcomes, expected);
+        let document = testing::read_part(&dir.join("Ani.docx"), "word/document.xml");
+        assert!(document.contains("Ani 6B"));
+
+        let outcomes = batch(&[budi], OverwritePolicy::Suffix);
+        let expected = [(dir.join("Budi-2.docx"), RowOutcome::Generated)];
+        assert_eq!(outcomes, expected);
+    }
+
+    #[test]
+    fn output_names_differing_in_case_are_the_same() {
+        let dir = TempDir::new("batch-name-case");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        let tokens = strings(&["{{nama}}"]);
+        let lines = lines(&["Budi", "BUDI"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+
+        let report = template
+            .build_docx_batch_report(&tokens, &lines, &pattern, &Default::default())
+            .unwrap();
+        let failed: Vec<_> = report.failed().map(|r| r.line).collect();
+        assert_eq!(failed, [1]);
+
+        let options = BatchOptions {
+            output_dir: Some(dir.join("zip")),
+            archive_pattern: Some("kelas.zip".to_owned()),
+            ..Default::default()
+        };
+        let err = template
+            .build_docx_batch(&tokens, &lines, "{{nama}}.docx", &options)
+            .unwrap_err();
+        assert!(matches!(err, DocxError::SameArchiveEntry { .. }));
+    }
+
+    #[test]
+    fn merged_and_archived_batches_reject_the_manifest() {
+        let dir = TempDir::new("batch-unresumable");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        let options = BatchOptions {
+            manifest: Some(dir.join("piagam.manifest.jsonl")),
+            archive_pattern: Some(dir.pattern("piagam.zip")),
+            ..Default::default()
+        };
+        let tokens = strings(&["{{nama}}"]);
+        let lines = lines(&["Budi"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+
+        let merged =
+            template.build_docx_merged(&tokens, &lines, &pattern, &options, &Default::default());
+        assert!(matches!(merged, Err(DocxError::ManifestUnsupported)));
+        let archived = template.build_docx_archives(&tokens, &lines, "{{nama}}.docx", &options);
+        assert!(matches!(archived, Err(DocxError::ManifestUnsupported)));
+        assert!(!dir.join("piagam.manifest.jsonl").exists());
+        assert!(!dir.join("Budi.docx").exists());
+    }
+
+    #[test]
+    fn merged_batch_registers_every_recipient() {
+        let dir = TempDir::new("batch-merged-registry");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        let options = BatchOptions {
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(dir.join("registry.json")),
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        let tokens = strings(&["{{nama}}"]);
+
+        let merged = template
+            .build_docx_merged(
+                &tokens,
+                &lines(&["Budi", "Ani", "Citra"]),
+                &dir.pattern("piagam-gabungan.docx"),
+                &options,
+                &Default::default(),
+            )
+            .unwrap();
+
+        let certificates = CertificateRegistry::new(dir.join("registry.json"))
+            .certificates()
+            .unwrap();
+        let names: Vec<&str> = certificates
+            .iter()
+            .map(|c| c.recipient["{{nama}}"].as_str())
+            .collect();
+        assert_eq!(names, ["Budi", "Ani", "Citra"]);
+        assert!(certificates.iter().all(|c| c.file == merged[0]));
+    }
+
+    #[test]
+    fn merged_documents_share_relationships_of_the_template() {
+        let dir = TempDir::new("batch-merged-rels");
+        let path = dir.join("piagam.docx");
+        let relationships = concat!(
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+            r#"<Relationship Id="rId5" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/logo.png"/>"#,
+            r#"<Relationship Id="rId6" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>"#,
+            r#"</Relationships>"#
+        );
+        let body = concat!(
+            r#"<w:p><w:bookmarkStart w:id="0" w:name="nama"/><w:r><w:t>{{nama}}</w:t></w:r>"#,
+            r#"<w:bookmarkEnd w:id="0"/></w:p>"#,
+            r#"<w:p><w:r><w:drawing><wp:inline><wp:docPr id="1" name="Logo"/>"#,
+            r#"<a:blip r:embed="rId5"/></wp:inline></w:drawing></w:r></w:p>"#,
+            r#"<w:p><w:pPr><w:sectPr><w:headerReference w:type="default" r:id="rId6"/>"#,
+            r#"</w:sectPr></w:pPr></w:p>"#,
+        );
+        write_template(
+            &path,
+            body,
+            &[
+                ("word/_rels/document.xml.rels", relationships),
+                ("word/header1.xml", "<w:hdr>{{sekolah}}</w:hdr>"),
+                ("word/media/logo.png", "PNG"),
+            ],
+        );
+        let template = DocxTemplate::open(&path).unwrap();
+        let tokens = strings(&["{{nama}}", "{{sekolah}}"]);
+        let lines = [strings(&["Budi", "SD 1"]), strings(&["Ani", "SD 1"])];
+
+        let merged = template
+            .build_docx_merged(
+                &tokens,
+                &lines,
+                &dir.pattern("{{sekolah}}.docx"),
+                &Default::default(),
+                &Default::default(),
+            )
+            .unwrap();
+
+        let document = testing::read_part(&merged[0], "word/document.xml");
+        assert_eq!(document.matches(r#"r:embed="rId5""#).count(), 2);
+        assert_eq!(document.matches(r#"r:id="rId6""#).count(), 2);
+        assert_eq!(document.matches(r#"<w:bookmarkStart w:id="2""#).count(), 1);
+        assert_eq!(document.matches(r#"<wp:docPr id="2""#).count(), 1);
+        let rels = testing::read_part(&merged[0], "word/_rels/document.xml.rels");
+        assert_eq!(rels, relationships);
+        let header = testing::read_part(&merged[0], "word/header1.xml");
+        assert_eq!(header, "<w:hdr>SD 1</w:hdr>");
+        assert_eq!(testing::read_part(&merged[0], "word/media/logo.png"), "PNG");
+    }
+
+    #[test]
+    fn merged_groups_fill_headers_and_properties_the_same_way() {
+        let dir = TempDir::new("batch-merged-header");
+        let path = dir.join("piagam.docx");
+        let footer = "<w:ftr>{{nama}}</w:ftr>";
+        let body = paragraph("{{nama}}");
+        write_template(&path, &body, &[("word/footer1.xml", footer)]);
+        let mut template = DocxTemplate::open(&path).unwrap();
+        let tokens = strings(&["{{nama}}", "{{sekolah}}"]);
+        let lines = [
+            strings(&["Budi", "SD 1"]),
+            strings(&["Ani", "SD 2"]),
+            strings(&["Budi", "SD 2"]),
+        ];
+        let merge = MergeOptions {
+            group_token: Some("{{nama}}".to_owned()),
+            ..Default::default()
+        };
+        let pattern = dir.pattern("{{nama}}.docx");
+        let merged = |template: &DocxTemplate, merge: &MergeOptions| {
+            template.build_docx_merged(&tokens, &lines, &pattern, &Default::default(), merge)
+        };
+
+        // line and the part differing within its group
+        let differing = |err: DocxError| match err {
+            DocxError::Line { line, source } => match *source {
+                DocxError::MergedPartDiffers { part } => Some((line, part)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let err = merged(&template, &Default::default()).unwrap_err();
+        assert_eq!(differing(err), Some((2, "word/footer1.xml".to_owned())));
+        assert!(!dir.join("Budi.docx").exists());
+
+        template.properties.title = Some("Piagam {{sekolah}}".to_owned());
+        let err = merged(&template, &merge).unwrap_err();
+        assert_eq!(differing(err), Some((3, "docProps/core.xml".to_owned())));
+
+        template.properties.title = Some("Piagam {{nama}}".to_owned());
+        let merged = merged(&template, &merge).unwrap();
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn archives_hold_documents_of_their_lines() {
+        let dir = TempDir::new("batch-archives");
+        let template =
+            template(&dir, &paragraph("{{nama}} {{_urut}}")).with_deterministic_output(true);
+        let tokens = strings(&["{{nama}}", "{{kelas}}"]);
+        let lines = [
+            strings(&["Budi", "6A"]),
+            strings(&["Ani", "6B"]),
+            strings(&["Citra", "6A"]),
+        ];
+        let mut options = BatchOptions {
+            output_dir: Some(dir.path().to_path_buf()),
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(dir.join("registry.json")),
+                key_tokens: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        let err = template
+            .build_docx_archives(&tokens, &lines, "{{nama}}.docx", &options)
+            .unwrap_err();
+        assert!(matches!(err, DocxError::MissingArchivePattern));
+        options.archive_pattern = Some("zip/{{kelas}}.zip".to_owned());
+
+        let archives = template
+            .build_docx_archives(&tokens, &lines, "{{nama}}.docx", &options)
+            .unwrap();
+
+        let expected = ["zip/6A.zip", "zip/6B.zip"].map(|name| dir.join(name));
+        assert_eq!(archives, expected);
+        let zip = fs::read(&archives[0]).unwrap();
+        let names = testing::part_names(&zip);
+        assert_eq!(names, ["Budi.docx", "Citra.docx", archive::INDEX_ENTRY]);
+        let index = testing::read_part_bytes(&zip, archive::INDEX_ENTRY);
+        let rows: Vec<&str> = index.lines().skip(1).collect();
+        assert_eq!(rows, ["1,Budi.docx,,Budi,6A", "3,Citra.docx,,Citra,6A"]);
+        let mut docx = Vec::new();
+        let mut zip_archive = zip::ZipArchive::new(Cursor::new(zip.as_slice())).unwrap();
+        let mut entry = zip_archive.by_name("Citra.docx").unwrap();
+        entry.read_to_end(&mut docx).unwrap();
+        let document = testing::read_part_bytes(&docx, "word/document.xml");
+        assert!(document.contains("Citra 3"));
+
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let certificate = registry.certificates().unwrap().remove(1);
+        assert_eq!(certificate.file, archives[0].join("Citra.docx"));
+        assert_eq!(certificate.sha256, store::sha256(&docx));
+
+        // the same documents make the same archives, kept as they are
+        options.overwrite = OverwritePolicy::IfChanged;
+        let modified = fs::metadata(&archives[0]).unwrap().modified().unwrap();
+        template
+            .build_docx_archives(&tokens, &lines, "{{nama}}.docx", &options)
+            .unwrap();
+        assert_eq!(fs::read(&archives[0]).unwrap(), zip);
+        let kept = fs::metadata(&archives[0]).unwrap().modified().unwrap();
+        assert_eq!(kept, modified);
+    }
+
+    /// Writer failing all the writes.
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for FailingWriter {
+        fn seek(&mut self, _: std::io::SeekFrom) -> std::io::Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn render_to_writes_the_same_package_as_the_file() {
+        let dir = TempDir::new("render-to");
+        let template = template(&dir, &paragraph("{{nama}}")).with_deterministic_output(true);
+        let tokens = strings(&["{{nama}}"]);
+        let values = strings(&["Budi"]);
+
+        let file = fs::File::create(dir.join("rendered.docx")).unwrap();
+        template.render_to(file, &tokens, &values).unwrap();
+        let path = template
+            .data_to_docx(&tokens, &values, &dir.pattern("{{nama}}.docx"))
+            .unwrap();
+
+        let rendered = fs::read(dir.join("rendered.docx")).unwrap();
+        assert_eq!(rendered, fs::read(path).unwrap());
+        assert_eq!(template.render_to_vec(&tokens, &values).unwrap(), rendered);
+        let document = testing::read_part_bytes(&rendered, "word/document.xml");
+        assert!(document.contains("<w:t>Budi</w:t>"));
+    }
+
+    #[test]
+    fn render_to_rejects_invalid_input_and_reports_writer_errors() {
+        let dir = TempDir::new("render-to-errors");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        let tokens = strings(&["{{nama}}"]);
+
+        let err = template
+            .render_to_vec(&tokens, &strings(&["Budi", "6A"]))
+            .unwrap_err();
+        assert!(matches!(err, DocxError::CountMismatch { values: 2, .. }));
+        let err = template.render_to_vec(&tokens, &[]).unwrap_err();
+        assert!(matches!(err, DocxError::MissingInput));
+        let err = template.render_to_vec(&[], &[]).unwrap_err();
+        assert!(matches!(err, DocxError::NoTokens));
+
+        let written = template.render_to(FailingWriter, &tokens, &strings(&["Budi"]));
+        assert!(matches!(written, Err(DocxError::Io(_) | DocxError::Zip(_))));
+    }
+
+    #[test]
+    fn deterministic_output_is_generated_at_fixed_time() {
+        let dir = TempDir::new("batch-deterministic");
+        let mut template = template(&dir, &paragraph("{{nama}} {{_waktu}}"));
+        let template_time = DateTime::from_date_and_time(2021, 3, 4, 5, 6, 8).unwrap();
+        for part in template.parts.iter_mut() {
+            part.last_modified = template_time;
+        }
+        let template_time = (template_time.datepart(), template_time.timepart());
+        let tokens = strings(&["{{nama}}"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+
+        template
+            .build_docx_batch(&tokens, &lines(&["Budi"]), &pattern, &Default::default())
+            .unwrap();
+        let generated = fs::read(dir.join("Budi.docx")).unwrap();
+        let time = raw_entry_time(&generated, "word/document.xml");
+        assert_eq!(time, template_time);
+
+        let template = template.with_deterministic_output(true);
+        template
+            .build_docx_batch(&tokens, &lines(&["Ani"]), &pattern, &Default::default())
+            .unwrap();
+        let generated = fs::read(dir.join("Ani.docx")).unwrap();
+        let document = testing::read_part_bytes(&generated, "word/document.xml");
+        assert!(document.contains("<w:t>Ani 01-01-1980 00.00</w:t>"));
+        let fixed = DateTime::default();
+        let fixed = (fixed.datepart(), fixed.timepart());
+        assert_eq!(raw_entry_time(&generated, "word/document.xml"), fixed);
+        let core = testing::read_part_bytes(&generated, properties::CORE_PROPERTIES_PART);
+        assert!(core.contains(properties::DETERMINISTIC_DATE));
+    }
+
+    /// Returns the timestamp (MS-DOS date and time) of the package entry.
+    fn raw_entry_time(docx: &[u8], name: &str) -> (u16, u16) {
+        let mut zip = zip::ZipArchive::new(Cursor::new(docx)).unwrap();
+        let time = zip.by_name(name).unwrap().last_modified();
+        (time.datepart(), time.timepart())
+    }
+
+    #[test]
+    fn generated_files_are_converted_into_pdf() {
+        let dir = TempDir::new("batch-convert");
+        let template = template(&dir, &paragraph("{{nama}}"));
+        let mut converter = testing::FakeConverter::default();
+        converter.failing.insert(dir.join("Ani.docx"));
+        let converter = Arc::new(converter);
+        let options = BatchOptions {
+            converter: Some(converter.clone()),
+            ..Default::default()
+        };
+        // the document of the last line is not generated, so it is not converted
+        fs::write(dir.join("Citra.docx"), b"kept").unwrap();
+        let tokens = strings(&["{{nama}}"]);
+        let lines = lines(&["Budi", "Ani", "Citra"]);
+        let pattern = dir.pattern("{{nama}}.docx");
+
+        let report = template
+            .build_docx_batch_report(&tokens, &lines, &pattern, &options)
+            .unwrap();
+
+        let expected = [dir.join("Budi.docx"), dir.join("Ani.docx")];
+        assert_eq!(converter.converted(), expected[..1]);
+        assert_eq!(report.rows[0].pdf, Some(Ok(dir.join("Budi.pdf"))));
+        assert!(dir.join("Budi.pdf").exists());
+        assert!(matches!(&report.rows[1].pdf, Some(Err(reason)) if !reason.is_empty()));
+        assert!(report.rows[1].is_generated());
+        assert!(report.rows[2].pdf.is_none());
+        let failed: Vec<_> = report.failed().map(|r| r.line).collect();
+        assert_eq!(failed, [1, 2]);
+        let result = report.into_result();
+        assert!(matches!(result, Err(DocxError::RowsFailed { rows, .. }) if rows.len() == 2));
+        assert!(!dir.join("Ani.pdf").exists());
+        assert!(expected.iter().all(|docx| docx.exists()));
+    }
+}
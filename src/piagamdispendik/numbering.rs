@@ -0,0 +1,265 @@
+use super::store::{self, StoreLock};
+use super::DocxResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+/// File name of the number registry in the application data folder.
+const REGISTRY_FILE: &str = "nomor.json";
+
+const ROMAN_MONTHS: [&str; 12] = [
+    "I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X", "XI", "XII",
+];
+
+/// Returns path of the number registry shared by all the batches of the current user.
+pub fn default_registry_path() -> PathBuf {
+    store::app_data_path(REGISTRY_FILE)
+}
+
+/// Format of the certificate numbers of one series, e.g. `421.3/0157/436.7.1/2026`.
+///
+/// The pattern may contain placeholders:
+/// `{prefix}`, `{seq}` (zero-padded counter), `{office}`, `{year}`, `{month}` (number) and `{roman}` (Roman month).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerialFormat {
+    /// key of the counter in the registry - numbers of the same series never repeat.
+    pub series: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub office: String,
+    /// minimal count of digits of the counter, padded with zeros.
+    #[serde(default)]
+    pub padding: usize,
+    #[serde(default = "default_pattern")]
+    pub pattern: String,
+    /// whether the counter starts over every year (the pattern should contain `{year}` then).
+    #[serde(default)]
+    pub yearly: bool,
+}
+
+fn default_pattern() -> String {
+    "{prefix}/{seq}/{office}/{year}".to_owned()
+}
+
+impl SerialFormat {
+    /// Formats the serial (counter value) into certificate number issued at the given date.
+    pub fn format(&self, serial: u64, date: OffsetDateTime) -> String {
+        let month = u8::from(date.month());
+        self.pattern
+            .replace("{prefix}", &self.prefix)
+            .replace("{office}", &self.office)
+            .replace("{seq}", &format!("{:0width$}", serial, width = self.padding))
+            .replace("{year}", &date.year().to_string())
+            .replace("{month}", &format!("{:02}", month))
+            .replace("{roman}", ROMAN_MONTHS[usize::from(month) - 1])
+    }
+
+    /// Registry key of the counter the numbers are allocated from.
+    fn counter_key(&self, date: OffsetDateTime) -> String {
+        if self.yearly {
+            format!("{}/{}", self.series, date.year())
+        } else {
+            self.series.clone()
+        }
+    }
+}
+
+/// Number assigned to a generated document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumberAssignment {
+    pub series: String,
+    pub serial: u64,
+    pub number: String,
+    pub assigned_at: String,
+    /// generated file carrying the number (empty until the file is written).
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NumberStore {
+    /// last allocated serial of each counter.
+    #[serde(default)]
+    counters: BTreeMap<String, u64>,
+    #[serde(default)]
+    assignments: Vec<NumberAssignment>,
+}
+
+/// Persistent local registry of certificate numbers - keeps the counters of all the series,
+/// so the numbers never repeat across batches or years.
+#[derive(Debug, Clone)]
+pub struct NumberRegistry {
+    path: PathBuf,
+}
+
+impl NumberRegistry {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the numbers the next `count` reservations would get, without reserving them.
+    pub fn preview(&self, format: &SerialFormat, count: usize) -> DocxResult<Vec<String>> {
+        let store: NumberStore = store::load_json(&self.path)?;
+        let date = store::now();
+        let last = store
+            .counters
+            .get(&format.counter_key(date))
+            .copied()
+            .unwrap_or(0);
+        Ok((1..=count as u64)
+            .map(|i| format.format(last + i, date))
+            .collect())
+    }
+
+    /// Atomically reserves `count` consecutive numbers of the series.
+    /// Reserved numbers are never given out again, even if the documents are not generated in the end.
+    ///
+    /// # Errors
+    ///
+    /// Can return errors when the registry cannot be locked, read or written.
+    pub fn reserve(&self, format: &SerialFormat, count: usize) -> DocxResult<Vec<NumberAssignment>> {
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut store: NumberStore = store::load_json(&self.path)?;
+        let date = store::now();
+        let assigned_at = store::timestamp(date);
+
+        let counter = store.counters.entry(format.counter_key(date)).or_insert(0);
+        let reserved: Vec<NumberAssignment> = (1..=count as u64)
+            .map(|i| NumberAssignment {
+                series: format.series.clone(),
+                serial: *counter + i,
+                number: format.format(*counter + i, date),
+                assigned_at: assigned_at.clone(),
+                file: None,
+            })
+            .collect();
+        *counter += count as u64;
+
+        store.assignments.extend(reserved.iter().cloned());
+        store::save_json(&self.path, &store)?;
+        Ok(reserved)
+    }
+
+    /// Records the generated files carrying the (previously reserved) numbers.
+    pub fn record_files(&self, files: &[(String, PathBuf)]) -> DocxResult<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut store: NumberStore = store::load_json(&self.path)?;
+        for (number, file) in files {
+            if let Some(assignment) = store
+                .assignments
+                .iter_mut()
+                .rev()
+                .find(|a| &a.number == number)
+            {
+                assignment.file = Some(file.to_owned());
+            }
+        }
+        store::save_json(&self.path, &store)
+    }
+
+    /// Returns all the numbers assigned so far.
+    pub fn assignments(&self) -> DocxResult<Vec<NumberAssignment>> {
+        let store: NumberStore = store::load_json(&self.path)?;
+        Ok(store.assignments)
+    }
+}
+
+/// Numbering of a batch - where the numbers are allocated from, and how they look like.
+#[derive(Debug, Clone)]
+pub struct Numbering {
+    pub registry: NumberRegistry,
+    pub format: SerialFormat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::TempDir;
+    use time::macros::datetime;
+
+    fn format(pattern: &str, padding: usize) -> SerialFormat {
+        SerialFormat {
+            series: "piagam".to_owned(),
+            prefix: "421.3".to_owned(),
+            office: "436.7.1".to_owned(),
+            padding,
+            pattern: pattern.to_owned(),
+            yearly: false,
+        }
+    }
+
+    #[test]
+    fn format_fills_all_placeholders() {
+        let date = datetime!(2026-10-19 10:00 UTC);
+
+        assert_eq!(
+            format(&default_pattern(), 4).format(157, date),
+            "421.3/0157/436.7.1/2026"
+        );
+        assert_eq!(
+            format("{seq}/{month}/{roman}/{year}", 0).format(12345, date),
+            "12345/10/X/2026"
+        );
+        assert_eq!(
+            format("{roman}-{roman}", 0).format(1, datetime!(2026-04-01 0:00 UTC)),
+            "IV-IV"
+        );
+    }
+
+    #[test]
+    fn yearly_counter_is_kept_per_year() {
+        let mut format = format(&default_pattern(), 0);
+        let date = datetime!(2026-01-01 0:00 UTC);
+        assert_eq!(format.counter_key(date), "piagam");
+
+        format.yearly = true;
+        assert_eq!(format.counter_key(date), "piagam/2026");
+    }
+
+    #[test]
+    fn reserved_numbers_never_repeat() {
+        let dir = TempDir::new("numbering");
+        let registry = NumberRegistry::new(dir.join("nomor.json"));
+        let format = format("{prefix}-{seq}", 3);
+
+        let preview = registry.preview(&format, 2).unwrap();
+        let first = registry.reserve(&format, 2).unwrap();
+        let second = registry.reserve(&format, 1).unwrap();
+
+        let numbers: Vec<&str> = first
+            .iter()
+            .chain(second.iter())
+            .map(|a| a.number.as_str())
+            .collect();
+        assert_eq!(preview, vec!["421.3-001", "421.3-002"]);
+        assert_eq!(numbers, vec!["421.3-001", "421.3-002", "421.3-003"]);
+        assert_eq!(registry.preview(&format, 1).unwrap(), vec!["421.3-004"]);
+    }
+
+    #[test]
+    fn record_files_sets_file_of_the_number() {
+        let dir = TempDir::new("numbering-files");
+        let registry = NumberRegistry::new(dir.join("nomor.json"));
+        let format = format("{seq}", 0);
+        registry.reserve(&format, 2).unwrap();
+
+        registry
+            .record_files(&[("2".to_owned(), PathBuf::from("budi.docx"))])
+            .unwrap();
+
+        let files: Vec<Option<PathBuf>> = registry
+            .assignments()
+            .unwrap()
+            .into_iter()
+            .map(|a| a.file)
+            .collect();
+        assert_eq!(files, vec![None, Some(PathBuf::from("budi.docx"))]);
+    }
+}
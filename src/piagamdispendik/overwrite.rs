@@ -0,0 +1,201 @@
+use super::{store, DocxError, DocxResult};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// What happens when the output file of a document exists already.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// the document fails, existing file is kept.
+    #[default]
+    Fail,
+    /// the document is not generated, existing file is kept.
+    Skip,
+    /// existing file is replaced.
+    Overwrite,
+    /// existing file is replaced only when its content differs from the generated document
+    /// (requires deterministic output of the template, otherwise the content always differs).
+    IfChanged,
+    /// the document is written to the first free name with numeric suffix (`piagam-2.docx`, ...).
+    Suffix,
+}
+
+/// Output file of a document, opened according to the overwrite policy.
+pub(super) enum OutputFile {
+    /// new empty file created, the path is reserved until the content is written (see [`write_output`]).
+    Created(PathBuf),
+    /// existing file, to be replaced by the content written (see [`write_output`]).
+    Replaced(PathBuf),
+    /// existing file left untouched (to be skipped or compared).
+    Existing(PathBuf),
+}
+
+impl OverwritePolicy {
+    /// Whether the batch validation rejects output files which exist already.
+    pub fn rejects_existing(self) -> bool {
+        self == OverwritePolicy::Fail
+    }
+
+    /// Opens the output file of a document.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::OutputExists when the file exists and the policy is [`OverwritePolicy::Fail`],
+    /// or IO errors.
+    pub(super) fn open(self, path: &Path) -> DocxResult<OutputFile> {
+        match (self, create_new(path)?) {
+            (_, true) => Ok(OutputFile::Created(path.to_path_buf())),
+            (OverwritePolicy::Fail, false) => Err(DocxError::OutputExists {
+                path: path.to_path_buf(),
+            }),
+            (OverwritePolicy::Skip, false) | (OverwritePolicy::IfChanged, false) => {
+                Ok(OutputFile::Existing(path.to_path_buf()))
+            }
+            (OverwritePolicy::Overwrite, false) => Ok(OutputFile::Replaced(path.to_path_buf())),
+            (OverwritePolicy::Suffix, false) => {
+                let mut suffix = 2;
+                loop {
+                    let suffixed = suffixed_path(path, suffix);
+                    if create_new(&suffixed)? {
+                        return Ok(OutputFile::Created(suffixed));
+                    }
+                    suffix += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Writes content of the output file - `write` writes it into a temporary file next to the output,
+/// which replaces the output once completely written. On failure the temporary file is removed,
+/// and so is the output file `reserved` for this content, so no partially written output is left behind.
+pub(super) fn write_output<T, F>(path: &Path, reserved: bool, write: F) -> DocxResult<T>
+where
+    F: FnOnce(File) -> DocxResult<T>,
+{
+    let tmp_path = store::sibling_path(path, "tmp");
+    let written = File::create(&tmp_path)
+        .map_err(DocxError::from)
+        .and_then(write)
+        .and_then(|value| {
+            fs::rename(&tmp_path, path)?;
+            Ok(value)
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        if reserved {
+            let _ = fs::remove_file(path);
+        }
+    }
+    written
+}
+
+/// Replaces content of the existing output file (see [`write_output`]).
+pub(super) fn replace_output(path: &Path, content: &[u8]) -> DocxResult<()> {
+    write_output(path, false, |mut file| Ok(file.write_all(content)?))
+}
+
+/// Creates the empty file, `false` when it exists already.
+fn create_new(path: &Path) -> DocxResult<bool> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns the path with numeric suffix of the file stem (`piagam.docx` -> `piagam-2.docx`).
+fn suffixed_path(path: &Path, suffix: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::TempDir;
+
+    #[test]
+    fn write_output_replaces_file_once_written() {
+        let dir = TempDir::new("write-output");
+        let path = dir.join("piagam.docx");
+        fs::write(&path, "old").unwrap();
+
+        let written = write_output(&path, false, |mut file| {
+            file.write_all(b"new")?;
+            Ok(3)
+        });
+        assert_eq!(written.unwrap(), 3);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!store::sibling_path(&path, "tmp").exists());
+    }
+
+    #[test]
+    fn failed_write_output_leaves_no_partial_file() {
+        let dir = TempDir::new("write-output-failed");
+        let failing = |mut file: File| -> DocxResult<()> {
+            file.write_all(b"partial")?;
+            Err(DocxError::NoBody)
+        };
+
+        let replaced = dir.join("replaced.docx");
+        fs::write(&replaced, "old").unwrap();
+        assert!(write_output(&replaced, false, failing).is_err());
+        assert_eq!(fs::read_to_string(&replaced).unwrap(), "old");
+
+        let reserved = dir.join("reserved.docx");
+        assert!(matches!(
+            OverwritePolicy::Fail.open(&reserved).unwrap(),
+            OutputFile::Created(_)
+        ));
+        assert!(write_output(&reserved, true, failing).is_err());
+        assert!(!reserved.exists());
+        assert!(!store::sibling_path(&reserved, "tmp").exists());
+    }
+
+    #[test]
+    fn open_follows_the_policy() {
+        let dir = TempDir::new("overwrite-open");
+        let path = dir.join("piagam.docx");
+        fs::write(&path, "old").unwrap();
+
+        assert!(matches!(
+            OverwritePolicy::Fail.open(&path),
+            Err(DocxError::OutputExists { path: p }) if p == path
+        ));
+        for policy in [OverwritePolicy::Skip, OverwritePolicy::IfChanged] {
+            assert!(matches!(policy.open(&path).unwrap(), OutputFile::Existing(p) if p == path));
+        }
+        assert!(matches!(
+            OverwritePolicy::Overwrite.open(&path).unwrap(),
+            OutputFile::Replaced(p) if p == path
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    }
+
+    #[test]
+    fn suffix_policy_reserves_first_free_name() {
+        let dir = TempDir::new("overwrite-suffix");
+        let path = dir.join("piagam.docx");
+        fs::write(&path, "old").unwrap();
+
+        for expected in ["piagam-2.docx", "piagam-3.docx"] {
+            let created = OverwritePolicy::Suffix.open(&path).unwrap();
+            assert!(matches!(created, OutputFile::Created(p) if p == dir.join(expected)));
+        }
+        assert!(dir.join("piagam-2.docx").exists());
+        assert_eq!(
+            suffixed_path(Path::new("out/README"), 2),
+            Path::new("out/README-2")
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use super::{DocxError, DocxResult};
+use std::collections::HashSet;
+use std::io::{Seek, Write};
+use zip::read::ZipFile;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+/// Name of the part declaring content types of all the other parts - the first entry of the package.
+pub const CONTENT_TYPES_PART: &str = "[Content_Types].xml";
+
+/// Single part (zip entry) of the DOCX package, as stored in the template.
+#[derive(Debug, Clone)]
+pub struct PackagePart {
+    pub name: String,
+    /// index of the entry in the template zip.
+    pub index: usize,
+    pub compression: CompressionMethod,
+    /// timestamp of the entry in the template zip.
+    pub last_modified: DateTime,
+}
+
+/// Whether the part is XML (content, relationships, properties...), rather than binary media or fonts.
+pub fn is_xml_part(name: &str) -> bool {
+    name.ends_with(".xml") || name.ends_with(".rels")
+}
+
+/// Orders the parts the way they are written into the generated packages -
+/// content types first, the others in their original order.
+pub fn order_parts(parts: &mut [PackagePart]) {
+    parts.sort_by_key(|part| part.name != CONTENT_TYPES_PART);
+}
+
+/// Writer of a DOCX (OPC) package, making sure each part is written exactly once.
+pub struct PackageWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    written: HashSet<String>,
+    /// timestamp of the new parts (the parts of the template keep their timestamps).
+    last_modified: DateTime,
+    /// whether all the written parts are timestamped by `last_modified` (deterministic output).
+    fixed_timestamps: bool,
+}
+
+impl<W: Write + Seek> PackageWriter<W> {
+    pub fn new(writer: W, last_modified: DateTime) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+            written: Default::default(),
+            last_modified,
+            fixed_timestamps: false,
+        }
+    }
+
+    /// Sets whether the parts of the template written are timestamped by the package timestamp too,
+    /// rather than keeping their timestamps (the copied parts always keep them).
+    pub fn with_fixed_timestamps(mut self, fixed: bool) -> Self {
+        self.fixed_timestamps = fixed;
+        self
+    }
+
+    /// Writes the part of the template with its original compression and timestamp
+    /// (see [`PackageWriter::with_fixed_timestamps`]).
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::DuplicatePart when the part was written already, or zip errors.
+    pub fn write_part(&mut self, part: &PackagePart, content: &[u8]) -> DocxResult<()> {
+        let last_modified = if self.fixed_timestamps {
+            self.last_modified
+        } else {
+            part.last_modified
+        };
+        self.start_part(&part.name, part.compression, last_modified)?;
+        self.zip.write_all(content)?;
+        Ok(())
+    }
+
+    /// Writes new part (not contained in the template), compressed and timestamped by the package timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::DuplicatePart when the part was written already, or zip errors.
+    pub fn add_part(&mut self, name: &str, content: &[u8]) -> DocxResult<()> {
+        self.start_part(name, CompressionMethod::Deflated, self.last_modified)?;
+        self.zip.write_all(content)?;
+        Ok(())
+    }
+
+    /// Copies the unchanged part from the template zip without decompressing it.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::DuplicatePart when the part was written already, or zip errors.
+    pub fn copy_part(&mut self, part: &PackagePart, raw: ZipFile) -> DocxResult<()> {
+        self.mark_written(&part.name)?;
+        self.zip.raw_copy_file(raw)?;
+        Ok(())
+    }
+
+    fn start_part(
+        &mut self,
+        name: &str,
+        compression: CompressionMethod,
+        last_modified: DateTime,
+    ) -> DocxResult<()> {
+        self.mark_written(name)?;
+        let options = FileOptions::default()
+            .compression_method(compression)
+            .last_modified_time(last_modified);
+        self.zip.start_file(name, options)?;
+        Ok(())
+    }
+
+    fn mark_written(&mut self, name: &str) -> DocxResult<()> {
+        if self.written.insert(name.to_owned()) {
+            return Ok(());
+        }
+        Err(DocxError::DuplicatePart {
+            part: name.to_owned(),
+        })
+    }
+
+    /// Finishes the package (writes the zip central directory), returns the underlying writer.
+    pub fn finish(mut self) -> DocxResult<W> {
+        Ok(self.zip.finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{part_names, read_part_bytes};
+    use std::io::Cursor;
+
+    fn part(name: &str, index: usize) -> PackagePart {
+        PackagePart {
+            name: name.to_owned(),
+            index,
+            compression: CompressionMethod::Deflated,
+            last_modified: DateTime::default(),
+        }
+    }
+
+    #[test]
+    fn order_parts_puts_content_types_first() {
+        let mut parts = vec![
+            part("_rels/.rels", 0),
+            part("word/document.xml", 1),
+            part(CONTENT_TYPES_PART, 2),
+            part("word/media/image1.png", 3),
+        ];
+        order_parts(&mut parts);
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                CONTENT_TYPES_PART,
+                "_rels/.rels",
+                "word/document.xml",
+                "word/media/image1.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn writer_rejects_duplicate_parts() {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new()), DateTime::default());
+        writer
+            .write_part(&part(CONTENT_TYPES_PART, 0), b"<Types/>")
+            .unwrap();
+        writer
+            .add_part("docProps/custom.xml", b"<Properties/>")
+            .unwrap();
+        assert!(matches!(
+            writer.add_part(CONTENT_TYPES_PART, b"<Types/>"),
+            Err(DocxError::DuplicatePart { part }) if part == CONTENT_TYPES_PART
+        ));
+        assert!(matches!(
+            writer.write_part(&part("docProps/custom.xml", 1), b""),
+            Err(DocxError::DuplicatePart { .. })
+        ));
+
+        let package = writer.finish().unwrap().into_inner();
+        assert_eq!(
+            part_names(&package),
+            vec![CONTENT_TYPES_PART, "docProps/custom.xml"]
+        );
+        assert_eq!(read_part_bytes(&package, CONTENT_TYPES_PART), "<Types/>");
+    }
+
+    #[test]
+    fn is_xml_part_recognizes_relationships() {
+        assert!(is_xml_part("word/document.xml"));
+        assert!(is_xml_part("word/_rels/document.xml.rels"));
+        assert!(!is_xml_part("word/media/image1.png"));
+    }
+
+    #[test]
+    fn written_parts_keep_template_timestamps_unless_fixed() {
+        let template_time = DateTime::from_date_and_time(2021, 3, 4, 5, 6, 8).unwrap();
+        let package_time = DateTime::from_date_and_time(2024, 1, 2, 3, 4, 6).unwrap();
+        let mut document = part("word/document.xml", 0);
+        document.last_modified = template_time;
+        let timestamps = |fixed: bool| -> Vec<(u16, u16)> {
+            let mut writer = PackageWriter::new(Cursor::new(Vec::new()), package_time)
+                .with_fixed_timestamps(fixed);
+            writer.write_part(&document, b"<w:document/>").unwrap();
+            writer.add_part("docProps/custom.xml", b"").unwrap();
+            let package = writer.finish().unwrap();
+            let mut zip = zip::ZipArchive::new(package).unwrap();
+            (0..zip.len())
+                .map(|i| zip.by_index(i).unwrap().last_modified())
+                .map(|time| (time.datepart(), time.timepart()))
+                .collect()
+        };
+        let template_time = (template_time.datepart(), template_time.timepart());
+        let package_time = (package_time.datepart(), package_time.timepart());
+
+        assert_eq!(timestamps(false), [template_time, package_time]);
+        assert_eq!(timestamps(true), [package_time, package_time]);
+    }
+}
@@ -0,0 +1,168 @@
+use super::{TokenPackArg, ValuePack, ValuePackArg};
+use std::path::{Component, Path, PathBuf};
+
+/// Characters not allowed in file and folder names (on Windows).
+const ILLEGAL_CHARS: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Names of the devices, not allowed as file or folder names (with any extension) on Windows.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Maximal length (in characters) of a single file or folder name of the output path.
+pub const MAX_COMPONENT_CHARS: usize = 120;
+
+/// Character used in place of the illegal ones.
+const REPLACEMENT: char = '_';
+
+/// Builds output path of a document out of the output pattern.
+/// Only the separators of the pattern split the path into folders - the values are filled in with
+/// the illegal characters (including separators) replaced, and each folder and file name
+/// filled with tokens is sanitized. Relative paths are resolved against the `root` directory.
+///
+/// # Arguments
+///
+/// * `root` - output root directory (the working directory when not set)
+/// * `output_pattern` - output file pattern, e.g. `{{kecamatan}}/{{sekolah}}/{{nama}}.docx`
+/// * `tokens` - vector of tokens to be replaced
+/// * `values` - vector of values to be filled in place of tokens
+pub fn output_path(
+    root: Option<&Path>,
+    output_pattern: &str,
+    tokens: TokenPackArg,
+    values: ValuePackArg,
+) -> PathBuf {
+    let values: ValuePack = values.iter().map(|value| replace_illegal(value)).collect();
+    let mut path = root.map(Path::to_path_buf).unwrap_or_default();
+    for component in Path::new(output_pattern).components() {
+        match component {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                let filled = super::replace_tokens(&name, tokens, &values);
+                if filled == name {
+                    path.push(name.as_ref());
+                } else {
+                    path.push(sanitize_component(&filled));
+                }
+            }
+            other => path.push(other.as_os_str()),
+        }
+    }
+    path
+}
+
+/// Makes the string a valid file or folder name - replaces the illegal and control characters,
+/// removes trailing dots and spaces, renames Windows reserved names and limits the length
+/// (keeping the extension).
+pub fn sanitize_component(name: &str) -> String {
+    let name = replace_illegal(name);
+    let mut name = name
+        .trim_start_matches(' ')
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if name.is_empty() {
+        return REPLACEMENT.to_string();
+    }
+
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        name.insert(0, REPLACEMENT);
+    }
+
+    if name.chars().count() > MAX_COMPONENT_CHARS {
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if dot > 0 && name.len() - dot <= 10 => name.split_at(dot),
+            _ => (name.as_str(), ""),
+        };
+        let keep = MAX_COMPONENT_CHARS.saturating_sub(extension.chars().count());
+        let stem: String = stem.chars().take(keep).collect();
+        name = format!("{}{}", stem.trim_end_matches(['.', ' ']), extension);
+    }
+    name
+}
+
+/// Replaces the characters illegal in file names (including path separators) and the control characters.
+fn replace_illegal(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if ILLEGAL_CHARS.contains(&c) || c.is_control() {
+                REPLACEMENT
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Builds path of a document within an archive out of the output pattern (see [`output_path`]).
+/// Root, drive and parent (`..`) parts of the path are left out, separators are always `/`.
+pub fn archive_entry(output_pattern: &str, tokens: TokenPackArg, values: ValuePackArg) -> String {
+    output_path(None, output_pattern, tokens, values)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::strings;
+
+    #[test]
+    fn sanitize_component_makes_valid_names() {
+        assert_eq!(sanitize_component("a/b:c*d?.docx"), "a_b_c_d_.docx");
+        assert_eq!(sanitize_component(" Budi\t. . "), "Budi_");
+        assert_eq!(sanitize_component("Budi\tSantoso"), "Budi_Santoso");
+        assert_eq!(sanitize_component(" ..."), "_");
+        assert_eq!(sanitize_component("con.docx"), "_con.docx");
+        assert_eq!(sanitize_component("LPT1 .docx"), "_LPT1 .docx");
+        assert_eq!(sanitize_component("console.docx"), "console.docx");
+    }
+
+    #[test]
+    fn sanitize_component_limits_length_keeping_extension() {
+        let long = "a".repeat(200) + ".docx";
+        let name = sanitize_component(&long);
+        assert_eq!(name.chars().count(), MAX_COMPONENT_CHARS);
+        assert!(name.ends_with("a.docx"));
+
+        let long = "ž".repeat(200);
+        assert_eq!(sanitize_component(&long), "ž".repeat(MAX_COMPONENT_CHARS));
+    }
+
+    #[test]
+    fn values_never_split_the_path() {
+        let tokens = strings(&["{{sekolah}}", "{{nama}}"]);
+        let values = strings(&["SD 1/2", "../Budi"]);
+
+        let path = output_path(
+            Some(Path::new("out")),
+            "{{sekolah}}/{{nama}}.docx",
+            &tokens,
+            &values,
+        );
+        assert_eq!(path, Path::new("out").join("SD 1_2").join(".._Budi.docx"));
+    }
+
+    #[test]
+    fn archive_entry_is_relative_with_forward_slashes() {
+        let tokens = strings(&["{{kelas}}", "{{nama}}"]);
+        let values = strings(&["6A", "Budi"]);
+
+        assert_eq!(
+            archive_entry("/../{{kelas}}/./{{nama}}.docx", &tokens, &values),
+            "6A/Budi.docx"
+        );
+        let entry = archive_entry("{{nama}}.docx", &tokens, &values);
+        assert_eq!(entry, "Budi.docx");
+    }
+}
@@ -0,0 +1,405 @@
+use super::constants::BatchConstants;
+use super::duplicates::DuplicateCheck;
+use super::merge::MergeOptions;
+use super::numbering::SerialFormat;
+use super::overwrite::OverwritePolicy;
+use super::properties::DocumentProperties;
+use super::{DocxError, DocxResult, StreamRow, TokenPack, TokenPackArg, ValuePack};
+use crate::lang;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// File name suffix of a mapping profile stored alongside its DOCX template.
+pub const PROFILE_EXTENSION: &str = "profile.json";
+
+/// Row filter of a mapping profile - only rows having `value` in `column` are used for generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowFilter {
+    pub column: String,
+    pub value: String,
+}
+
+impl RowFilter {
+    fn accepts(&self, header: &[String], row: &[String]) -> bool {
+        match header.iter().position(|h| h == &self.column) {
+            Some(idx) => row.get(idx).map(|v| v.trim()) == Some(self.value.trim()),
+            None => false,
+        }
+    }
+}
+
+/// Problem found when a profile is checked against tokens of the currently loaded template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileWarning {
+    /// Token is mapped by the profile, but the template does not contain it.
+    UnknownToken(String),
+    /// Token of the template is neither mapped to a column nor given a constant value.
+    UnmappedToken(String),
+}
+
+impl ProfileWarning {
+    /// Returns localized textual representation of the warning.
+    pub fn to_message(&self) -> String {
+        let (msg_id, token) = match self {
+            ProfileWarning::UnknownToken(token) => ("profile-unknown-token", token),
+            ProfileWarning::UnmappedToken(token) => ("profile-unmapped-token", token),
+        };
+        let args: lang::TrArgVec = vec![("token".to_string(), token.to_string())];
+        lang::tr_with_args(msg_id, &args)
+    }
+}
+
+/// Reusable token-to-column mapping of a recurring event (template + data layout).
+///
+/// Profiles are stored as JSON next to the DOCX template (see [`profile_path`]),
+/// and applied whenever the same template is loaded with data having matching header.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MappingProfile {
+    /// token -> header column the token value is taken from.
+    #[serde(default)]
+    pub columns: BTreeMap<String, String>,
+
+    /// rows not passing all of the filters are left out of generation.
+    #[serde(default)]
+    pub filters: Vec<RowFilter>,
+
+    /// token -> value used for every generated document.
+    #[serde(default)]
+    pub constants: BTreeMap<String, String>,
+
+    /// output file pattern (explicit string or pattern contains tokens)
+    #[serde(default)]
+    pub output_pattern: String,
+
+    /// root directory of the output files (folder of the template when not set).
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+
+    /// format of the certificate numbers assigned to the documents (no numbering when not set).
+    #[serde(default)]
+    pub numbering: Option<SerialFormat>,
+
+    /// tokens identifying the recipient in the issued-certificate registry (all the data tokens when empty).
+    #[serde(default)]
+    pub recipient_tokens: Vec<String>,
+
+    /// how the duplicate recipients are detected before the generation.
+    #[serde(default)]
+    pub duplicates: DuplicateCheck,
+
+    /// what happens when output file of a document exists already.
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+
+    /// the generated files are reproducible - same data always yields byte-identical documents
+    /// (see [`super::DocxTemplate::deterministic`]).
+    #[serde(default)]
+    pub deterministic: bool,
+
+    /// properties set in each generated document (title, author, certificate number...).
+    #[serde(default)]
+    pub properties: DocumentProperties,
+
+    /// the generated documents are converted into PDF by LibreOffice, when set.
+    #[serde(default)]
+    pub convert_pdf: bool,
+
+    /// the batch is merged into a single document (or one per group) for printing, when set.
+    #[serde(default)]
+    pub merge: Option<MergeOptions>,
+
+    /// the documents are written into ZIP archives of this pattern, when set
+    /// (see [`super::BatchOptions::archive_pattern`]).
+    #[serde(default)]
+    pub archive_pattern: Option<String>,
+}
+
+impl MappingProfile {
+    /// Creates profile out of the current selection - n-th token is filled from n-th header column.
+    pub fn from_selection(tokens: TokenPackArg, header: &[String], output_pattern: &str) -> Self {
+        Self {
+            columns: tokens
+                .iter()
+                .zip(header.iter())
+                .map(|(token, column)| (token.to_owned(), column.to_owned()))
+                .collect(),
+            output_pattern: output_pattern.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Loads the profile from JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> DocxResult<Self> {
+        let reader = BufReader::new(File::open(path.as_ref())?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Loads the profile stored alongside the template, if there is any.
+    pub fn load_for_template<P: AsRef<Path>>(template: P) -> DocxResult<Option<Self>> {
+        let path = profile_path(template);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::load(path).map(Some)
+    }
+
+    /// Stores the profile into JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> DocxResult<()> {
+        let writer = BufWriter::new(File::create(path.as_ref())?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Returns constant values of the profile, to be filled in every document of the batch.
+    pub fn batch_constants(&self) -> BatchConstants {
+        let mut constants = BatchConstants::default();
+        for (token, value) in self.constants.iter() {
+            constants.insert(token, value);
+        }
+        constants
+    }
+
+    /// Whether all the columns the profile refers to are present in the data header.
+    pub fn matches_header(&self, header: &[String]) -> bool {
+        self.columns
+            .values()
+            .chain(self.filters.iter().map(|f| &f.column))
+            .all(|column| header.contains(column))
+    }
+
+    /// Checks the profile against tokens found in the template.
+    pub fn check_tokens(&self, template_tokens: TokenPackArg) -> Vec<ProfileWarning> {
+        let mut warnings: Vec<ProfileWarning> = Default::default();
+        for token in self.columns.keys().chain(self.constants.keys()) {
+            if !template_tokens.contains(token) {
+                warnings.push(ProfileWarning::UnknownToken(token.to_owned()));
+            }
+        }
+        for token in template_tokens {
+            if !self.columns.contains_key(token) && !self.constants.contains_key(token) {
+                warnings.push(ProfileWarning::UnmappedToken(token.to_owned()));
+            }
+        }
+        warnings
+    }
+
+    /// Returns indexes of the rows passing all the profile filters.
+    pub fn accepted_rows(&self, header: &[String], rows: &[ValuePack]) -> Vec<usize> {
+        (0..rows.len())
+            .filter(|idx| self.filters.iter().all(|f| f.accepts(header, &rows[*idx])))
+            .collect()
+    }
+
+    /// Builds tokens and per-row values for generation out of the loaded data.
+    /// Only tokens present in the template are used, rows are filtered by profile filters.
+    /// Tokens having constant value are included in the returned tokens, but not in the row values
+    /// (see [`MappingProfile::batch_constants`]).
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::MissingColumn when the data header misses a column the profile refers to.
+    pub fn apply(
+        &self,
+        template_tokens: TokenPackArg,
+        header: &[String],
+        rows: &[ValuePack],
+    ) -> DocxResult<(TokenPack, Vec<ValuePack>)> {
+        let (tokens, indexes) = self.columns_of(template_tokens, header)?;
+        let values = self
+            .accepted_rows(header, rows)
+            .into_iter()
+            .map(|row| project(&indexes, &rows[row]))
+            .collect();
+
+        Ok((tokens, values))
+    }
+
+    /// Builds tokens and projects the streamed rows (source data row number and values) the way
+    /// [`MappingProfile::apply`] does, rows are projected (and filtered) only as they are consumed.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::MissingColumn when the data header misses a column the profile refers to.
+    pub fn apply_stream<'a, I>(
+        &'a self,
+        template_tokens: TokenPackArg,
+        header: &'a [String],
+        rows: I,
+    ) -> DocxResult<(TokenPack, impl Iterator<Item = DocxResult<StreamRow>> + 'a)>
+    where
+        I: IntoIterator<Item = DocxResult<StreamRow>> + 'a,
+    {
+        let (tokens, indexes) = self.columns_of(template_tokens, header)?;
+        let rows = rows.into_iter().filter_map(move |row| match row {
+            Ok((row, values)) if self.filters.iter().all(|f| f.accepts(header, &values)) => {
+                Some(Ok((row, project(&indexes, &values))))
+            }
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        });
+        Ok((tokens, rows))
+    }
+
+    /// Returns tokens filled by the profile (present in the template, the constant ones last),
+    /// and index of the data column of each of the tokens filled from data.
+    fn columns_of(
+        &self,
+        template_tokens: TokenPackArg,
+        header: &[String],
+    ) -> DocxResult<(TokenPack, Vec<usize>)> {
+        let mut tokens: TokenPack = Default::default();
+        let mut indexes: Vec<usize> = Default::default();
+        for (token, column) in self.columns.iter() {
+            if !template_tokens.contains(token) || self.constants.contains_key(token) {
+                continue;
+            }
+            match header.iter().position(|h| h == column) {
+                Some(idx) => {
+                    tokens.push(token.to_owned());
+                    indexes.push(idx);
+                }
+                None => {
+                    return Err(DocxError::MissingColumn {
+                        column: column.to_owned(),
+                    });
+                }
+            }
+        }
+
+        tokens.extend(
+            self.constants
+                .keys()
+                .filter(|token| template_tokens.contains(token))
+                .cloned(),
+        );
+        Ok((tokens, indexes))
+    }
+}
+
+/// Returns values of the data columns of the row (missing ones empty).
+fn project(indexes: &[usize], row: &[String]) -> ValuePack {
+    indexes
+        .iter()
+        .map(|idx| row.get(*idx).cloned().unwrap_or_default())
+        .collect()
+}
+
+/// Returns path of the profile stored alongside the template (`piagam.docx` -> `piagam.profile.json`).
+pub fn profile_path<P: AsRef<Path>>(template: P) -> PathBuf {
+    template.as_ref().with_extension(PROFILE_EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{strings, TempDir};
+
+    fn profile() -> MappingProfile {
+        MappingProfile {
+            columns: [("{{nama}}", "Nama"), ("{{sekolah}}", "Sekolah")]
+                .iter()
+                .map(|(token, column)| (token.to_string(), column.to_string()))
+                .collect(),
+            filters: vec![RowFilter {
+                column: "Lulus".to_owned(),
+                value: "ya".to_owned(),
+            }],
+            constants: [("{{tanggal}}".to_owned(), "1 Mei 2026".to_owned())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_projects_filtered_rows_onto_template_tokens() {
+        let header = strings(&["Sekolah", "Lulus", "Nama"]);
+        let rows = vec![
+            strings(&["SDN 1", "ya", "Budi"]),
+            strings(&["SDN 2", "tidak", "Ani"]),
+            strings(&["SDN 3", " ya ", "Citra"]),
+        ];
+        let template_tokens = strings(&["{{nama}}", "{{sekolah}}", "{{tanggal}}"]);
+
+        let (tokens, values) = profile().apply(&template_tokens, &header, &rows).unwrap();
+
+        assert_eq!(tokens, strings(&["{{nama}}", "{{sekolah}}", "{{tanggal}}"]));
+        assert_eq!(
+            values,
+            vec![strings(&["Budi", "SDN 1"]), strings(&["Citra", "SDN 3"])]
+        );
+        assert_eq!(profile().accepted_rows(&header, &rows), vec![0, 2]);
+    }
+
+    #[test]
+    fn apply_stream_projects_rows_as_they_are_consumed() {
+        let header = strings(&["Sekolah", "Lulus", "Nama"]);
+        let rows = vec![
+            Ok((2, strings(&["SDN 1", "ya", "Budi"]))),
+            Ok((3, strings(&["SDN 2", "tidak", "Ani"]))),
+            Err(DocxError::MissingInput),
+            Ok((5, strings(&["SDN 3", "ya"]))),
+        ];
+        let template_tokens = strings(&["{{nama}}", "{{sekolah}}", "{{tanggal}}"]);
+        let profile = profile();
+
+        let (tokens, rows) = profile
+            .apply_stream(&template_tokens, &header, rows)
+            .unwrap();
+        let rows: Vec<DocxResult<StreamRow>> = rows.collect();
+
+        assert_eq!(tokens, strings(&["{{nama}}", "{{sekolah}}", "{{tanggal}}"]));
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].as_ref().unwrap(), &(2, strings(&["Budi", "SDN 1"])));
+        assert!(rows[1].is_err());
+        assert_eq!(rows[2].as_ref().unwrap(), &(5, strings(&["", "SDN 3"])));
+    }
+
+    #[test]
+    fn apply_fails_on_missing_column() {
+        let header = strings(&["Nama", "Lulus"]);
+        let template_tokens = strings(&["{{nama}}", "{{sekolah}}"]);
+
+        let err = profile().apply(&template_tokens, &header, &[]).unwrap_err();
+
+        assert!(matches!(err, DocxError::MissingColumn { column } if column == "Sekolah"));
+        assert!(!profile().matches_header(&header));
+    }
+
+    #[test]
+    fn check_tokens_reports_unknown_and_unmapped_tokens() {
+        let warnings = profile().check_tokens(&strings(&["{{nama}}", "{{kelas}}"]));
+
+        assert_eq!(
+            warnings,
+            vec![
+                ProfileWarning::UnknownToken("{{sekolah}}".to_owned()),
+                ProfileWarning::UnknownToken("{{tanggal}}".to_owned()),
+                ProfileWarning::UnmappedToken("{{kelas}}".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn profile_is_stored_alongside_template() {
+        let dir = TempDir::new("profile");
+        let template = dir.join("piagam.docx");
+        assert_eq!(MappingProfile::load_for_template(&template).unwrap(), None);
+
+        let profile = MappingProfile::from_selection(
+            &strings(&["{{nama}}", "{{sekolah}}"]),
+            &strings(&["Nama", "Sekolah", "Kelas"]),
+            "{{nama}}.docx",
+        );
+        profile.save(profile_path(&template)).unwrap();
+
+        assert_eq!(dir.join("piagam.profile.json"), profile_path(&template));
+        assert_eq!(
+            MappingProfile::load_for_template(&template).unwrap(),
+            Some(profile)
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use super::{FileMap, TokenPackArg, ValuePackArg};
+use regex::{Captures, NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::from_utf8;
+use time::macros::{datetime, format_description};
+use time::{OffsetDateTime, UtcOffset};
+
+lazy_static! {
+    /// Creation and modification dates of the document, in the core properties part.
+    static ref CORE_DATE: Regex =
+        Regex::new(r"(<dcterms:(?:created|modified)\b[^>]*>)[^<]*(</dcterms:(?:created|modified)>)")
+            .expect("valid core date regex");
+    /// Single custom property, with its (escaped) name.
+    static ref CUSTOM_PROPERTY: Regex =
+        Regex::new(r#"(?s)<property\b[^>]*\bname="([^"]*)"[^>]*>.*?</property>"#)
+            .expect("valid custom property regex");
+    /// Id of a custom property, the ids are numbered from 2 in the order of the properties.
+    static ref PROPERTY_ID: Regex = Regex::new(r#"\bpid="\d+""#).expect("valid property id regex");
+}
+
+/// Name of the part with the core properties (title, author, dates...) of the document.
+pub const CORE_PROPERTIES_PART: &str = "docProps/core.xml";
+
+/// Name of the part with the custom properties of the document.
+pub const CUSTOM_PROPERTIES_PART: &str = "docProps/custom.xml";
+
+/// Name of the part with the package relationships (the properties parts are referenced from it).
+const PACKAGE_RELATIONSHIPS_PART: &str = "_rels/.rels";
+
+const CUSTOM_PROPERTIES_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.custom-properties+xml";
+const CUSTOM_PROPERTIES_RELATIONSHIP: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+/// Format id all the user defined custom properties share.
+const CUSTOM_PROPERTY_FORMAT: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+
+/// Creation and modification date of the documents generated with deterministic output
+/// (the earliest date a zip entry can have).
+pub const DETERMINISTIC_DATE: &str = "1980-01-01T00:00:00Z";
+
+/// Generation time of the documents with deterministic output (the [`DETERMINISTIC_DATE`]).
+pub const DETERMINISTIC_TIME: OffsetDateTime = datetime!(1980-01-01 0:00 UTC);
+
+/// Properties set in each generated document. The values are patterns filled with the tokens
+/// of the document (e.g. `Piagam {{nama}}`), the properties not set are kept as in the template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentProperties {
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub subject: Option<String>,
+
+    /// author and last editor of the document, e.g. the issuing office.
+    #[serde(default)]
+    pub author: Option<String>,
+
+    /// custom properties by their names, e.g. `Nomor` -> `{{nomor}}` (see [`super::context`]).
+    #[serde(default)]
+    pub custom: BTreeMap<String, String>,
+}
+
+impl DocumentProperties {
+    /// Returns the properties of a document - the patterns filled with its values.
+    pub fn fill(&self, tokens: TokenPackArg, values: ValuePackArg) -> Self {
+        let fill = |pattern: &String| super::replace_tokens(pattern, tokens, values);
+        Self {
+            title: self.title.as_ref().map(fill),
+            subject: self.subject.as_ref().map(fill),
+            author: self.author.as_ref().map(fill),
+            custom: self
+                .custom
+                .iter()
+                .map(|(name, pattern)| (name.clone(), fill(pattern)))
+                .collect(),
+        }
+    }
+
+    /// Returns the properties part the (filled) properties differ in from the other ones, if any.
+    pub(super) fn differing_part(&self, other: &Self) -> Option<&'static str> {
+        let core = (&self.title, &self.subject, &self.author);
+        if core != (&other.title, &other.subject, &other.author) {
+            Some(CORE_PROPERTIES_PART)
+        } else if self.custom != other.custom {
+            Some(CUSTOM_PROPERTIES_PART)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the package parts changed by the (filled) properties and the generation `date` -
+    /// the core properties, the custom properties and the parts referencing them when they are new.
+    pub(super) fn parts(&self, file_data: &FileMap, date: &str) -> BTreeMap<String, String> {
+        let part = |name: &str| {
+            file_data
+                .get(name)
+                .and_then(|content| from_utf8(content).ok())
+        };
+        let mut parts: BTreeMap<String, String> = Default::default();
+        if let Some(core) = part(CORE_PROPERTIES_PART) {
+            parts.insert(CORE_PROPERTIES_PART.to_owned(), self.core_xml(core, date));
+        }
+        if self.custom.is_empty() {
+            return parts;
+        }
+
+        let custom = part(CUSTOM_PROPERTIES_PART);
+        parts.insert(CUSTOM_PROPERTIES_PART.to_owned(), self.custom_xml(custom));
+        if custom.is_none() {
+            if let Some(types) = part(super::package::CONTENT_TYPES_PART) {
+                let types = types.replacen("</Types>", &custom_content_type(), 1);
+                parts.insert(super::package::CONTENT_TYPES_PART.to_owned(), types);
+            }
+            if let Some(rels) = part(PACKAGE_RELATIONSHIPS_PART) {
+                let rels = rels.replacen("</Relationships>", &custom_relationship(rels), 1);
+                parts.insert(PACKAGE_RELATIONSHIPS_PART.to_owned(), rels);
+            }
+        }
+        parts
+    }
+
+    /// Sets the dates, title, subject and author of the core properties part.
+    fn core_xml(&self, core: &str, date: &str) -> String {
+        let mut core = set_core_dates(core, date);
+        let elements = [
+            ("dc:title", &self.title),
+            ("dc:subject", &self.subject),
+            ("dc:creator", &self.author),
+            ("cp:lastModifiedBy", &self.author),
+        ];
+        for (element, value) in elements {
+            if let Some(value) = value {
+                core = set_element(&core, element, &escape(value));
+            }
+        }
+        core
+    }
+
+    /// Sets the custom properties - the template ones of the same names are replaced,
+    /// the others are kept.
+    fn custom_xml(&self, custom: Option<&str>) -> String {
+        let custom = custom.unwrap_or(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" "#,
+            r#"xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"></Properties>"#
+        ));
+        let names: Vec<String> = self.custom.keys().map(|name| escape(name)).collect();
+        let kept = CUSTOM_PROPERTY.replace_all(custom, |caps: &Captures| {
+            if names.iter().any(|name| name == &caps[1]) {
+                String::new()
+            } else {
+                caps[0].to_owned()
+            }
+        });
+        let added: String = names
+            .iter()
+            .zip(self.custom.values())
+            .map(|(name, value)| {
+                format!(
+                    r#"<property fmtid="{}" pid="0" name="{}"><vt:lpwstr>{}</vt:lpwstr></property>"#,
+                    CUSTOM_PROPERTY_FORMAT,
+                    name,
+                    escape(value)
+                )
+            })
+            .collect();
+        let custom = kept.replacen("</Properties>", &format!("{}</Properties>", added), 1);
+        let mut pid = 1;
+        PROPERTY_ID
+            .replace_all(&custom, |_: &Captures| {
+                pid += 1;
+                format!(r#"pid="{}""#, pid)
+            })
+            .into_owned()
+    }
+}
+
+/// Formats the time the way the core properties keep the dates (W3CDTF, in UTC).
+pub fn w3c_date(date: OffsetDateTime) -> String {
+    let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]Z");
+    date.to_offset(UtcOffset::UTC)
+        .format(&format)
+        .unwrap_or_else(|_| DETERMINISTIC_DATE.to_owned())
+}
+
+/// Replaces the creation and modification dates in the core properties part.
+pub fn set_core_dates(xml: &str, date: &str) -> String {
+    CORE_DATE
+        .replace_all(xml, |caps: &Captures| {
+            format!("{}{}{}", &caps[1], date, &caps[2])
+        })
+        .into_owned()
+}
+
+/// Sets content of the element of the core properties, the element is added when missing.
+fn set_element(core: &str, element: &str, value: &str) -> String {
+    let pattern = format!(
+        r"<{0}(?:\s[^>]*)?(?:/>|>[^<]*</{0}>)",
+        regex::escape(element)
+    );
+    let replacement = format!("<{0}>{1}</{0}>", element, value);
+    match Regex::new(&pattern) {
+        Ok(existing) if existing.is_match(core) => {
+            // the value is the user's text, `$` in it is not a reference to the captures
+            existing.replace(core, NoExpand(&replacement)).into_owned()
+        }
+        _ => core.replacen(
+            "</cp:coreProperties>",
+            &format!("{}</cp:coreProperties>", replacement),
+            1,
+        ),
+    }
+}
+
+/// Content type declaration of the custom properties part.
+fn custom_content_type() -> String {
+    format!(
+        r#"<Override PartName="/{}" ContentType="{}"/></Types>"#,
+        CUSTOM_PROPERTIES_PART, CUSTOM_PROPERTIES_CONTENT_TYPE
+    )
+}
+
+/// Relationship to the custom properties part, with an id not used by the other relationships.
+fn custom_relationship(rels: &str) -> String {
+    let id = (1..)
+        .map(|n| format!("rId{}", n))
+        .find(|id| !rels.contains(&format!(r#"Id="{}""#, id)))
+        .unwrap_or_default();
+    format!(
+        r#"<Relationship Id="{}" Type="{}" Target="{}"/></Relationships>"#,
+        id, CUSTOM_PROPERTIES_RELATIONSHIP, CUSTOM_PROPERTIES_PART
+    )
+}
+
+/// Escapes the text to be placed into XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::package::CONTENT_TYPES_PART;
+    use crate::piagamdispendik::testing::{strings, write_template, TempDir, CORE_PROPERTIES};
+    use crate::piagamdispendik::DocxTemplate;
+
+    fn custom(properties: &[(&str, &str)]) -> DocumentProperties {
+        DocumentProperties {
+            custom: properties
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the XML parts of a template with the extra parts.
+    fn file_data(dir: &TempDir, extra: &[(&str, &str)]) -> FileMap {
+        let path = dir.join("piagam.docx");
+        write_template(&path, "", extra);
+        DocxTemplate::open(&path).unwrap().file_data
+    }
+
+    #[test]
+    fn fill_fills_all_the_patterns() {
+        let mut properties = custom(&[("Nomor", "{{_nomor}}")]);
+        properties.title = Some("Piagam {{nama}}".to_owned());
+        properties.author = Some("Dispendik".to_owned());
+
+        let tokens = strings(&["{{nama}}", "{{_nomor}}"]);
+        let filled = properties.fill(&tokens, &strings(&["Budi", "7"]));
+
+        assert_eq!(filled.title.as_deref(), Some("Piagam Budi"));
+        assert_eq!(filled.subject, None);
+        assert_eq!(filled.author.as_deref(), Some("Dispendik"));
+        assert_eq!(filled.custom["Nomor"], "7");
+    }
+
+    #[test]
+    fn core_xml_sets_escaped_elements_and_dates() {
+        let properties = DocumentProperties {
+            title: Some("Budi & <Ani>".to_owned()),
+            subject: Some("Lomba \"Cerdas\"".to_owned()),
+            author: Some("Dispendik".to_owned()),
+            ..Default::default()
+        };
+
+        let core = properties.core_xml(CORE_PROPERTIES, DETERMINISTIC_DATE);
+
+        assert!(core.contains("<dc:title>Budi &amp; &lt;Ani&gt;</dc:title>"));
+        // the missing elements are added
+        assert!(core.contains("<dc:subject>Lomba &quot;Cerdas&quot;</dc:subject>"));
+        let editor = "<cp:lastModifiedBy>Dispendik</cp:lastModifiedBy>";
+        assert!(core.ends_with(&format!("{}</cp:coreProperties>", editor)));
+        assert_eq!(core.matches("<dc:creator>").count(), 1);
+        assert_eq!(core.matches(DETERMINISTIC_DATE).count(), 2);
+        assert!(!core.contains("2022-01-0"));
+        assert_eq!(w3c_date(DETERMINISTIC_TIME), DETERMINISTIC_DATE);
+    }
+
+    #[test]
+    fn core_xml_keeps_dollar_signs() {
+        let properties = DocumentProperties {
+            title: Some("Juara $1 Rp$100".to_owned()),
+            subject: Some("${nama} $0".to_owned()),
+            author: Some("$$ Dispendik".to_owned()),
+            ..Default::default()
+        };
+
+        // the second time, all the elements are there to be replaced
+        let core = properties.core_xml(CORE_PROPERTIES, DETERMINISTIC_DATE);
+        let core = properties.core_xml(&core, DETERMINISTIC_DATE);
+
+        assert_eq!(core.matches("<dc:subject>").count(), 1);
+        assert!(core.contains("<dc:title>Juara $1 Rp$100</dc:title>"));
+        assert!(core.contains("<dc:subject>${nama} $0</dc:subject>"));
+        assert!(core.contains("<dc:creator>$$ Dispendik</dc:creator>"));
+        assert!(core.contains("<cp:lastModifiedBy>$$ Dispendik</cp:lastModifiedBy>"));
+    }
+
+    #[test]
+    fn custom_xml_replaces_escaped_properties_and_numbers_them() {
+        let template = concat!(
+            r#"<Properties><property fmtid="x" pid="5" name="Sekolah"><vt:lpwstr>SD 1</vt:lpwstr></property>"#,
+            r#"<property fmtid="x" pid="9" name="A&amp;B"><vt:lpwstr>old</vt:lpwstr></property></Properties>"#
+        );
+        let properties = custom(&[("A&B", "<7>")]);
+
+        let xml = properties.custom_xml(Some(template));
+
+        assert!(xml.contains(r#"pid="2" name="Sekolah"><vt:lpwstr>SD 1</vt:lpwstr>"#));
+        assert!(xml.contains(r#"pid="3" name="A&amp;B"><vt:lpwstr>&lt;7&gt;</vt:lpwstr>"#));
+        assert!(!xml.contains("old"));
+
+        let xml = properties.custom_xml(None);
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(r#"pid="2" name="A&amp;B""#));
+    }
+
+    #[test]
+    fn new_custom_part_is_declared_and_referenced() {
+        let dir = TempDir::new("properties-new-custom");
+        let file_data = file_data(&dir, &[]);
+
+        let parts = custom(&[("Nomor", "7")]).parts(&file_data, DETERMINISTIC_DATE);
+
+        let names: Vec<&str> = parts.keys().map(|name| name.as_str()).collect();
+        let expected = [
+            CONTENT_TYPES_PART,
+            PACKAGE_RELATIONSHIPS_PART,
+            CORE_PROPERTIES_PART,
+            CUSTOM_PROPERTIES_PART,
+        ];
+        assert_eq!(names, expected);
+        let types = &parts[CONTENT_TYPES_PART];
+        assert!(types.ends_with(&custom_content_type()));
+        assert!(types.contains(r#"<Override PartName="/docProps/custom.xml" "#));
+        let rels = &parts[PACKAGE_RELATIONSHIPS_PART];
+        assert!(rels.contains(r#"<Relationship Id="rId3" "#));
+        assert!(rels.contains(r#"Target="docProps/custom.xml"/></Relationships>"#));
+    }
+
+    #[test]
+    fn existing_custom_part_is_only_replaced() {
+        let dir = TempDir::new("properties-existing-custom");
+        let existing = (CUSTOM_PROPERTIES_PART, "<Properties></Properties>");
+        let file_data = file_data(&dir, &[existing]);
+
+        let parts = custom(&[("Nomor", "7")]).parts(&file_data, DETERMINISTIC_DATE);
+        let names: Vec<&str> = parts.keys().map(|name| name.as_str()).collect();
+        assert_eq!(names, [CORE_PROPERTIES_PART, CUSTOM_PROPERTIES_PART]);
+
+        let parts = DocumentProperties::default().parts(&file_data, DETERMINISTIC_DATE);
+        let names: Vec<&str> = parts.keys().map(|name| name.as_str()).collect();
+        assert_eq!(names, [CORE_PROPERTIES_PART]);
+    }
+}
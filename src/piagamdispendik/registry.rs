@@ -0,0 +1,716 @@
+use super::properties::DocumentProperties;
+use super::store::{self, StoreLock};
+use super::{context, DocxError, DocxResult, DocxTemplate, GeneratedDocument};
+use super::{TokenPack, TokenPackArg, ValuePack, ValuePackArg};
+use crate::lang;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the issued-certificate registry in the application data folder.
+const REGISTRY_FILE: &str = "registry.json";
+
+/// Returns path of the issued-certificate registry shared by all the batches of the current user.
+pub fn default_registry_path() -> PathBuf {
+    store::app_data_path(REGISTRY_FILE)
+}
+
+/// Current state of an issued certificate.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "state")]
+pub enum CertificateStatus {
+    #[default]
+    Active,
+    /// Certificate was withdrawn.
+    Revoked { reason: String, revoked_at: String },
+    /// Certificate was replaced by a corrected one (identified by its SHA-256).
+    Superseded { by: String },
+}
+
+/// Record of a single generated certificate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssuedCertificate {
+    /// certificate number (empty when the batch was generated without numbering).
+    #[serde(default)]
+    pub number: String,
+    /// token -> value of the fields identifying the recipient.
+    pub recipient: BTreeMap<String, String>,
+    pub template: PathBuf,
+    pub file: PathBuf,
+    pub issued_at: String,
+    /// hex encoded SHA-256 of the generated file.
+    pub sha256: String,
+    /// token -> value of all the tokens the certificate was generated with - the line values,
+    /// the batch constants and the row context (used for reissue).
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+    /// settings of the template the certificate was generated with (used for reissue).
+    #[serde(default)]
+    pub generation: GenerationSettings,
+    /// revision of the certificate, `0` for the originally issued one.
+    #[serde(default)]
+    pub revision: u32,
+    /// SHA-256 of the certificate this one is a corrected version of.
+    #[serde(default)]
+    pub supersedes: Option<String>,
+    #[serde(default)]
+    pub status: CertificateStatus,
+}
+
+impl IssuedCertificate {
+    /// Creates the record of a freshly generated file.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - path of the DOCX template the file is generated from
+    /// * `file` - path of the generated file
+    /// * `number` - assigned certificate number, if any
+    /// * `recipient` - tokens and values identifying the recipient
+    pub fn new(
+        template: &Path,
+        file: &Path,
+        number: Option<&str>,
+        recipient: (TokenPackArg, ValuePackArg),
+    ) -> DocxResult<Self> {
+        let sha256 = store::sha256_file(file)?;
+        Ok(Self::with_digest(template, file, sha256, number, recipient))
+    }
+
+    /// Creates the record of a generated document whose digest is known already
+    /// (e.g. document stored in an archive, `file` is its path within the archive).
+    pub fn with_digest(
+        template: &Path,
+        file: &Path,
+        sha256: String,
+        number: Option<&str>,
+        recipient: (TokenPackArg, ValuePackArg),
+    ) -> Self {
+        let (tokens, values) = recipient;
+        Self {
+            number: number.unwrap_or_default().to_owned(),
+            recipient: tokens.iter().cloned().zip(values.iter().cloned()).collect(),
+            template: template.to_path_buf(),
+            file: file.to_path_buf(),
+            issued_at: store::timestamp(store::now()),
+            sha256,
+            values: Default::default(),
+            generation: Default::default(),
+            revision: 0,
+            supersedes: None,
+            status: CertificateStatus::Active,
+        }
+    }
+
+    /// Whether the certificate is identified by the id - its number or SHA-256.
+    fn is_identified_by(&self, id: &str) -> bool {
+        let id = id.trim();
+        (!self.number.is_empty() && self.number == id) || self.sha256 == id
+    }
+
+    /// Returns verification result of presented certificate matching this record.
+    fn verification(&self) -> Verification {
+        match self.status {
+            CertificateStatus::Active => Verification::Genuine(self.clone()),
+            CertificateStatus::Revoked { .. } => Verification::Revoked(self.clone()),
+            CertificateStatus::Superseded { .. } => Verification::Superseded(self.clone()),
+        }
+    }
+
+    /// Whether the records are of the same certificate - the same file and content, number
+    /// and recipient (documents of a merged batch share the file, each is a certificate of its own).
+    fn is_same_as(&self, other: &IssuedCertificate) -> bool {
+        self.sha256 == other.sha256
+            && self.file == other.file
+            && self.number == other.number
+            && self.recipient == other.recipient
+    }
+
+    /// Whether any of the recipient fields equals the name (ignoring case and extra whitespace).
+    fn has_recipient_name(&self, name: &str) -> bool {
+        let name = normalize(name);
+        self.recipient.values().any(|v| normalize(v) == name)
+    }
+}
+
+/// Settings of the template the certificate was generated with, beside the values filled into it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSettings {
+    /// see [`DocxTemplate::deterministic`].
+    #[serde(default)]
+    pub deterministic: bool,
+    /// see [`DocxTemplate::properties`].
+    #[serde(default)]
+    pub properties: DocumentProperties,
+}
+
+impl GenerationSettings {
+    /// Returns the settings of the template.
+    fn of(template: &DocxTemplate) -> Self {
+        Self {
+            deterministic: template.deterministic,
+            properties: template.properties.clone(),
+        }
+    }
+
+    /// Applies the settings to the template.
+    fn apply(&self, template: DocxTemplate) -> DocxTemplate {
+        template
+            .with_deterministic_output(self.deterministic)
+            .with_properties(self.properties.clone())
+    }
+}
+
+/// Result of the certificate verification against the registry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    /// The certificate matches the registry entry exactly.
+    Genuine(IssuedCertificate),
+    /// The certificate carries a registered number, but its content differs from the issued one.
+    Altered(IssuedCertificate),
+    /// The number is registered, but for a different recipient.
+    RecipientMismatch(IssuedCertificate),
+    /// The certificate was withdrawn.
+    Revoked(IssuedCertificate),
+    /// The certificate was replaced by a corrected version.
+    Superseded(IssuedCertificate),
+    /// Nothing matching the certificate was found in the registry.
+    Unknown,
+}
+
+impl Verification {
+    /// Returns localized textual representation of the verification result.
+    pub fn to_message(&self) -> String {
+        let (msg_id, certificate) = match self {
+            Verification::Genuine(c) => ("verify-genuine", c),
+            Verification::Altered(c) => ("verify-altered", c),
+            Verification::RecipientMismatch(c) => ("verify-recipient-mismatch", c),
+            Verification::Revoked(c) => ("verify-revoked", c),
+            Verification::Superseded(c) => ("verify-superseded", c),
+            Verification::Unknown => return lang::tr("verify-unknown"),
+        };
+        let reason = match &certificate.status {
+            CertificateStatus::Revoked { reason, .. } => reason.clone(),
+            _ => String::new(),
+        };
+        let args: lang::TrArgVec = vec![
+            ("number".to_string(), certificate.number.clone()),
+            ("date".to_string(), certificate.issued_at.clone()),
+            (
+                "filename".to_string(),
+                certificate.file.display().to_string(),
+            ),
+            ("reason".to_string(), reason),
+        ];
+        lang::tr_with_args(msg_id, &args)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryStore {
+    #[serde(default)]
+    certificates: Vec<IssuedCertificate>,
+}
+
+impl RegistryStore {
+    /// Returns index of the active certificate identified by the id (number or SHA-256).
+    fn active_index(&self, id: &str) -> DocxResult<usize> {
+        let found = self
+            .certificates
+            .iter()
+            .rposition(|c| c.is_identified_by(id) && c.status == CertificateStatus::Active);
+        match found {
+            Some(idx) => Ok(idx),
+            None => Err(DocxError::NoActiveCertificate { id: id.to_owned() }),
+        }
+    }
+}
+
+/// Persistent local registry of all the generated certificates, used for offline verification.
+#[derive(Debug, Clone)]
+pub struct CertificateRegistry {
+    path: PathBuf,
+}
+
+impl CertificateRegistry {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Adds records of generated certificates into the registry.
+    /// Certificates already registered (same file and its content, number and recipient) are not
+    /// added again - e.g. documents of a resumed batch, recorded with the values (timestamp) of the
+    /// resumed run.
+    pub fn record(&self, certificates: Vec<IssuedCertificate>) -> DocxResult<()> {
+        if certificates.is_empty() {
+            return Ok(());
+        }
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut store: RegistryStore = store::load_json(&self.path)?;
+        for certificate in certificates {
+            let registered = &store.certificates;
+            if !registered.iter().any(|c| c.is_same_as(&certificate)) {
+                store.certificates.push(certificate);
+            }
+        }
+        store::save_json(&self.path, &store)
+    }
+
+    /// Returns all the registered certificates.
+    pub fn certificates(&self) -> DocxResult<Vec<IssuedCertificate>> {
+        let store: RegistryStore = store::load_json(&self.path)?;
+        Ok(store.certificates)
+    }
+
+    /// Verifies the DOCX file - whether it is exactly one of the generated certificates,
+    /// or it carries a registered number but was altered afterwards.
+    pub fn verify_file<P: AsRef<Path>>(&self, file: P) -> DocxResult<Verification> {
+        let certificates = self.certificates()?;
+        let digest = store::sha256_file(file.as_ref())?;
+        if let Some(found) = certificates.iter().rev().find(|c| c.sha256 == digest) {
+            return Ok(found.verification());
+        }
+
+        let document = DocxTemplate::open(file.as_ref())?.document_contents()?;
+        let claimed = certificates
+            .iter()
+            .rev()
+            .find(|c| !c.number.is_empty() && document.contains(&c.number));
+        Ok(match claimed {
+            Some(found) => Verification::Altered(found.clone()),
+            None => Verification::Unknown,
+        })
+    }
+
+    /// Verifies the certificate number presented together with the recipient name.
+    /// All the revisions of the number are considered, the latest one matching the name decides.
+    pub fn verify_number(&self, number: &str, name: &str) -> DocxResult<Verification> {
+        let certificates = self.certificates()?;
+        let revisions: Vec<&IssuedCertificate> = certificates
+            .iter()
+            .filter(|c| !c.number.is_empty() && c.number == number.trim())
+            .collect();
+        let matching = revisions.iter().rev().find(|c| c.has_recipient_name(name));
+        Ok(match (matching, revisions.last()) {
+            (Some(c), _) => c.verification(),
+            (None, Some(c)) => Verification::RecipientMismatch((*c).clone()),
+            (None, None) => Verification::Unknown,
+        })
+    }
+
+    /// Marks the active certificate identified by its number or SHA-256 as revoked.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::NoActiveCertificate when there is no such active certificate.
+    pub fn revoke(&self, id: &str, reason: &str) -> DocxResult<IssuedCertificate> {
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut store: RegistryStore = store::load_json(&self.path)?;
+        let idx = store.active_index(id)?;
+        store.certificates[idx].status = CertificateStatus::Revoked {
+            reason: reason.to_owned(),
+            revoked_at: store::timestamp(store::now()),
+        };
+        let revoked = store.certificates[idx].clone();
+        store::save_json(&self.path, &store)?;
+        Ok(revoked)
+    }
+
+    /// Regenerates corrected version of the active certificate identified by its number or SHA-256.
+    /// The new document is generated from the same template, with the same settings and values
+    /// (updated by the corrections), keeps the original number and carries the revision
+    /// in [`context::TOKEN_REVISION`]. The original certificate is marked as superseded by the new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - number or SHA-256 of the certificate to be reissued
+    /// * `corrections` - tokens and their corrected values
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::NoActiveCertificate when there is no such active certificate,
+    /// Docx::ArchivedCertificate when the certificate is stored in an archive,
+    /// or errors of the document generation.
+    pub fn reissue(
+        &self,
+        id: &str,
+        corrections: &[(String, String)],
+    ) -> DocxResult<IssuedCertificate> {
+        let _lock = StoreLock::acquire(&self.path)?;
+        let mut store: RegistryStore = store::load_json(&self.path)?;
+        let idx = store.active_index(id)?;
+        let original = store.certificates[idx].clone();
+        // the path of an archived certificate is its path within the archive (file)
+        if original.file.ancestors().skip(1).any(|path| path.is_file()) {
+            return Err(DocxError::ArchivedCertificate {
+                path: original.file,
+            });
+        }
+
+        let revision = original.revision + 1;
+        let mut values = original.values.clone();
+        for (token, value) in corrections {
+            values.insert(token.to_owned(), value.to_owned());
+        }
+        values.insert(context::TOKEN_REVISION.to_owned(), revision.to_string());
+        let (tokens, values): (TokenPack, ValuePack) = values.into_iter().unzip();
+
+        let file = revision_path(&original.file, revision);
+        let template = original
+            .generation
+            .apply(DocxTemplate::open(&original.template)?);
+        let file = template.data_to_docx(&tokens, &values, &file.to_string_lossy())?;
+
+        let recipient: (TokenPack, ValuePack) = tokens
+            .iter()
+            .zip(values.iter())
+            .filter(|(token, _)| original.recipient.contains_key(*token))
+            .map(|(token, value)| (token.to_owned(), value.to_owned()))
+            .unzip();
+        let mut reissued = IssuedCertificate::new(
+            &original.template,
+            &file,
+            Some(&original.number),
+            (&recipient.0, &recipient.1),
+        )?;
+        reissued.values = tokens.into_iter().zip(values).collect();
+        reissued.generation = original.generation.clone();
+        reissued.revision = revision;
+        reissued.supersedes = Some(original.sha256.clone());
+
+        store.certificates[idx].status = CertificateStatus::Superseded {
+            by: reissued.sha256.clone(),
+        };
+        store.certificates.push(reissued.clone());
+        store::save_json(&self.path, &store)?;
+        Ok(reissued)
+    }
+}
+
+/// Registration of a batch - where the generated certificates are recorded,
+/// and which tokens identify the recipient (all the data tokens when empty).
+#[derive(Debug, Clone)]
+pub struct Registration {
+    pub registry: CertificateRegistry,
+    pub key_tokens: Vec<String>,
+}
+
+impl Registration {
+    /// Creates the registry record of a generated document.
+    fn certificate(
+        &self,
+        template: &Path,
+        document: &GeneratedDocument,
+    ) -> DocxResult<IssuedCertificate> {
+        let (tokens, values): (TokenPack, ValuePack) = document
+            .tokens
+            .iter()
+            .zip(document.values.iter())
+            .filter(|(token, _)| {
+                if self.key_tokens.is_empty() {
+                    !context::is_reserved(token)
+                } else {
+                    self.key_tokens.contains(token)
+                }
+            })
+            .map(|(token, value)| (token.to_owned(), value.to_owned()))
+            .unzip();
+        let number = document.number.as_deref();
+        let mut certificate = match &document.sha256 {
+            Some(sha256) => IssuedCertificate::with_digest(
+                template,
+                &document.path,
+                sha256.clone(),
+                number,
+                (&tokens, &values),
+            ),
+            None => IssuedCertificate::new(template, &document.path, number, (&tokens, &values))?,
+        };
+        certificate.values = document
+            .tokens
+            .iter()
+            .cloned()
+            .zip(document.values.iter().cloned())
+            .collect();
+        Ok(certificate)
+    }
+
+    /// Records all the generated documents of a batch into the registry.
+    pub(super) fn record(
+        &self,
+        template: &DocxTemplate,
+        documents: &[GeneratedDocument],
+    ) -> DocxResult<()> {
+        let generation = GenerationSettings::of(template);
+        let certificates = documents
+            .iter()
+            .map(|document| {
+                let mut certificate = self.certificate(&template.input_path, document)?;
+                certificate.generation = generation.clone();
+                Ok(certificate)
+            })
+            .collect::<DocxResult<Vec<IssuedCertificate>>>()?;
+        self.registry.record(certificates)
+    }
+}
+
+/// Returns path of the reissued certificate next to the original (`budi.docx`, `budi.rev1.docx` -> `budi.rev2.docx`).
+fn revision_path(original: &Path, revision: u32) -> PathBuf {
+    let stem = original
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = match stem.rsplit_once(".rev") {
+        Some((base, rev)) if rev.parse::<u32>().is_ok() => base.to_owned(),
+        _ => stem,
+    };
+    original.with_file_name(format!("{}.rev{}.docx", stem, revision))
+}
+
+/// Normalizes the value for comparison - collapses the whitespace and ignores the case.
+pub(super) fn normalize(value: &str) -> String {
+    value
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::{paragraph, strings, write_template, TempDir};
+    use std::fs;
+
+    /// Generates certificate of the recipient from the template, returns its record.
+    fn issue(dir: &TempDir, name: &str, number: &str) -> IssuedCertificate {
+        let template = dir.join("piagam.docx");
+        if !template.exists() {
+            write_template(&template, &paragraph("{{nama}} {{_nomor}}"), &[]);
+        }
+        let tokens = strings(&["{{nama}}", context::TOKEN_NUMBER]);
+        let values = strings(&[name, number]);
+        let file = DocxTemplate::open(&template)
+            .unwrap()
+            .data_to_docx(&tokens, &values, &dir.pattern(&format!("{}.docx", name)))
+            .unwrap();
+        IssuedCertificate::new(&template, &file, Some(number), (&tokens[..1], &values[..1]))
+            .unwrap()
+    }
+
+    #[test]
+    fn record_adds_each_certificate_once() {
+        let dir = TempDir::new("registry-record");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi", "421/001");
+        let ani = issue(&dir, "Ani", "421/002");
+
+        registry.record(vec![budi.clone()]).unwrap();
+        registry.record(vec![budi.clone(), ani.clone()]).unwrap();
+
+        assert_eq!(registry.certificates().unwrap(), vec![budi, ani]);
+    }
+
+    #[test]
+    fn record_ignores_values_of_registered_file() {
+        let dir = TempDir::new("registry-record-resumed");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi", "421/001");
+        let mut resumed = budi.clone();
+        resumed.values = [(context::TOKEN_TIMESTAMP.to_owned(), "later".to_owned())].into();
+        let mut copy = budi.clone();
+        copy.file = dir.join("salinan.docx");
+
+        registry.record(vec![budi.clone()]).unwrap();
+        registry.record(vec![resumed, copy.clone()]).unwrap();
+
+        assert_eq!(registry.certificates().unwrap(), vec![budi, copy]);
+    }
+
+    #[test]
+    fn record_keeps_every_recipient_of_merged_file() {
+        let dir = TempDir::new("registry-record-merged");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi", "421/001");
+        // recipients of the other pages of the same merged file
+        let merged: Vec<IssuedCertificate> = [("Ani", "421/002"), ("Citra", "421/003")]
+            .iter()
+            .map(|(name, number)| {
+                let mut certificate = budi.clone();
+                certificate.number = number.to_string();
+                certificate.recipient = [("{{nama}}".to_owned(), name.to_string())].into();
+                certificate
+            })
+            .chain([budi.clone()])
+            .collect();
+
+        registry.record(vec![budi.clone()]).unwrap();
+        registry.record(merged.clone()).unwrap();
+
+        let certificates = registry.certificates().unwrap();
+        assert_eq!(certificates, [&[budi], &merged[..2]].concat());
+    }
+
+    #[test]
+    fn verify_file_detects_genuine_altered_and_unknown_files() {
+        let dir = TempDir::new("registry-verify-file");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi", "421/001");
+        registry.record(vec![budi.clone()]).unwrap();
+
+        let genuine = registry.verify_file(&budi.file).unwrap();
+        assert_eq!(genuine, Verification::Genuine(budi.clone()));
+
+        // same number, different content
+        let altered = issue(&dir, "Budiman", "421/001");
+        let verification = registry.verify_file(&altered.file).unwrap();
+        assert_eq!(verification, Verification::Altered(budi));
+
+        let unknown = issue(&dir, "Ani", "421/002");
+        let verification = registry.verify_file(&unknown.file).unwrap();
+        assert_eq!(verification, Verification::Unknown);
+    }
+
+    #[test]
+    fn verify_number_checks_recipient_name() {
+        let dir = TempDir::new("registry-verify-number");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi Santoso", "421/001");
+        registry.record(vec![budi.clone()]).unwrap();
+
+        assert_eq!(
+            registry.verify_number(" 421/001", "budi  SANTOSO").unwrap(),
+            Verification::Genuine(budi.clone())
+        );
+        assert_eq!(
+            registry.verify_number("421/001", "Ani").unwrap(),
+            Verification::RecipientMismatch(budi)
+        );
+        assert_eq!(
+            registry.verify_number("421/999", "Budi Santoso").unwrap(),
+            Verification::Unknown
+        );
+    }
+
+    #[test]
+    fn registration_leaves_reserved_tokens_out_of_recipient() {
+        let dir = TempDir::new("registry-registration");
+        let file = dir.join("budi.docx");
+        fs::write(&file, "docx").unwrap();
+        let document = GeneratedDocument {
+            number: Some("421/001".to_owned()),
+            tokens: strings(&["{{nama}}", "{{sekolah}}", context::TOKEN_SEQUENCE]),
+            values: strings(&["Budi", "SDN 1", "1"]),
+            path: file,
+            sha256: None,
+        };
+        let mut registration = Registration {
+            registry: CertificateRegistry::new(dir.join("registry.json")),
+            key_tokens: Vec::new(),
+        };
+
+        let certificate = registration
+            .certificate(Path::new("t.docx"), &document)
+            .unwrap();
+        assert_eq!(certificate.recipient.len(), 2);
+        assert_eq!(certificate.values.len(), 3);
+        assert_eq!(certificate.sha256, store::sha256(b"docx"));
+
+        registration.key_tokens = strings(&["{{nama}}"]);
+        let certificate = registration
+            .certificate(Path::new("t.docx"), &document)
+            .unwrap();
+        assert_eq!(
+            certificate.recipient.into_iter().collect::<Vec<_>>(),
+            vec![("{{nama}}".to_owned(), "Budi".to_owned())]
+        );
+    }
+
+    #[test]
+    fn revoke_marks_only_active_certificate() {
+        let dir = TempDir::new("registry-revoke");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let budi = issue(&dir, "Budi", "421/001");
+        registry.record(vec![budi.clone()]).unwrap();
+
+        let revoked = registry.revoke("421/001", "salah nama").unwrap();
+        assert!(matches!(
+            &revoked.status,
+            CertificateStatus::Revoked { reason, .. } if reason == "salah nama"
+        ));
+        assert_eq!(
+            registry.verify_file(&budi.file).unwrap(),
+            Verification::Revoked(revoked)
+        );
+        assert!(matches!(
+            registry.revoke(&budi.sha256, "lagi"),
+            Err(DocxError::NoActiveCertificate { .. })
+        ));
+    }
+
+    #[test]
+    fn reissue_supersedes_original_with_corrected_revision() {
+        let dir = TempDir::new("registry-reissue");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let mut budi = issue(&dir, "Budi", "421/001");
+        budi.values = [("{{nama}}", "Budi"), (context::TOKEN_NUMBER, "421/001")]
+            .iter()
+            .map(|(token, value)| (token.to_string(), value.to_string()))
+            .collect();
+        registry.record(vec![budi.clone()]).unwrap();
+
+        let corrections = vec![("{{nama}}".to_owned(), "Budi Santoso".to_owned())];
+        let reissued = registry.reissue("421/001", &corrections).unwrap();
+        assert_eq!(reissued.revision, 1);
+        assert_eq!(reissued.number, "421/001");
+        assert_eq!(reissued.file, dir.join("Budi.rev1.docx"));
+        assert_eq!(reissued.supersedes.as_deref(), Some(budi.sha256.as_str()));
+        assert_eq!(reissued.recipient["{{nama}}"], "Budi Santoso");
+
+        let certificates = registry.certificates().unwrap();
+        assert_eq!(
+            certificates[0].status,
+            CertificateStatus::Superseded {
+                by: reissued.sha256.clone()
+            }
+        );
+        assert_eq!(
+            registry.verify_number("421/001", "Budi Santoso").unwrap(),
+            Verification::Genuine(reissued)
+        );
+        assert!(matches!(
+            registry.verify_number("421/001", "Budi").unwrap(),
+            Verification::Superseded(_)
+        ));
+    }
+
+    #[test]
+    fn reissue_rejects_archived_certificate() {
+        let dir = TempDir::new("registry-reissue-archived");
+        let registry = CertificateRegistry::new(dir.join("registry.json"));
+        let mut budi = issue(&dir, "Budi", "421/001");
+        fs::write(dir.join("piagam.zip"), "zip").unwrap();
+        budi.file = dir.join("piagam.zip").join("Budi.docx");
+        registry.record(vec![budi.clone()]).unwrap();
+
+        let corrections = vec![("{{nama}}".to_owned(), "Budi Santoso".to_owned())];
+        assert!(matches!(
+            registry.reissue("421/001", &corrections),
+            Err(DocxError::ArchivedCertificate { path }) if path == budi.file
+        ));
+        assert_eq!(registry.certificates().unwrap(), vec![budi]);
+    }
+
+    #[test]
+    fn revision_path_replaces_previous_revision() {
+        assert_eq!(
+            revision_path(Path::new("out/budi.docx"), 1),
+            Path::new("out/budi.rev1.docx")
+        );
+        assert_eq!(
+            revision_path(Path::new("out/budi.rev1.docx"), 2),
+            Path::new("out/budi.rev2.docx")
+        );
+    }
+}
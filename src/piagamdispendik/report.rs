@@ -0,0 +1,146 @@
+use super::{DocxError, DocxResult};
+use crate::lang;
+use std::path::{Path, PathBuf};
+
+/// File name suffix of the batch report stored alongside the DOCX template.
+pub const REPORT_EXTENSION: &str = "report.csv";
+
+/// Outcome of a single line of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowReport {
+    /// line of the batch values.
+    pub line: usize,
+    /// number of the row in the source data.
+    pub row: usize,
+    /// output file of the line (not known when the values of the line are invalid).
+    pub path: Option<PathBuf>,
+    pub outcome: RowOutcome,
+    /// PDF converted from the output file, or reason of the conversion failure
+    /// (not set when the output file was not converted, see [`super::BatchOptions::converter`]).
+    pub pdf: Option<Result<PathBuf, String>>,
+}
+
+/// What happened to a line of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowOutcome {
+    Generated,
+    /// generated by the interrupted run of a resumed batch (see [`super::manifest`]).
+    Resumed,
+    /// existing output file was replaced by the document.
+    Overwritten,
+    /// existing output file is the same as the document, it was kept.
+    Unchanged,
+    /// existing output file was kept, the document was not generated.
+    Skipped,
+    /// the document was not generated, for the reason given.
+    Failed(String),
+}
+
+impl RowReport {
+    /// Whether the output file holds the document of the line (generated now or before).
+    pub fn is_generated(&self) -> bool {
+        !matches!(self.outcome, RowOutcome::Failed(_) | RowOutcome::Skipped)
+    }
+
+    /// Returns reason the document was not generated, or was not converted into PDF.
+    pub fn error(&self) -> Option<&str> {
+        match (&self.outcome, &self.pdf) {
+            (RowOutcome::Failed(reason), _) | (_, Some(Err(reason))) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+/// Report of a batch generated in continue-on-error mode - outcome of each line, in order of the lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchReport {
+    pub rows: Vec<RowReport>,
+    /// reason the generated documents could not be recorded into the registries
+    /// (see [`super::BatchOptions::registration`]), the documents stay generated.
+    pub record_failure: Option<String>,
+}
+
+impl BatchReport {
+    /// Returns the lines generated successfully.
+    pub fn generated(&self) -> impl Iterator<Item = &RowReport> {
+        self.rows.iter().filter(|r| r.is_generated())
+    }
+
+    /// Returns the lines that failed to be generated or converted.
+    pub fn failed(&self) -> impl Iterator<Item = &RowReport> {
+        self.rows.iter().filter(|r| r.error().is_some())
+    }
+
+    /// Returns the lines skipped because of their existing output files.
+    pub fn skipped(&self) -> impl Iterator<Item = &RowReport> {
+        self.rows
+            .iter()
+            .filter(|r| r.outcome == RowOutcome::Skipped)
+    }
+
+    /// Whether some of the lines failed, or the generated documents were not recorded.
+    pub fn has_failures(&self) -> bool {
+        self.failed().next().is_some() || self.record_failure.is_some()
+    }
+
+    /// Exports the report as CSV (one line per row, with localized header and status),
+    /// so the data can be fixed and the failed rows generated again.
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> DocxResult<()> {
+        let mut writer = csv::Writer::from_path(path.as_ref())?;
+        writer.write_record([
+            lang::tr("report-column-row"),
+            lang::tr("report-column-status"),
+            lang::tr("report-column-file"),
+            lang::tr("report-column-reason"),
+            lang::tr("report-column-pdf"),
+        ])?;
+        for row in self.rows.iter() {
+            let status = match row.outcome {
+                _ if matches!(row.pdf, Some(Err(_))) => lang::tr("report-status-conversion-failed"),
+                RowOutcome::Generated => lang::tr("report-status-generated"),
+                RowOutcome::Resumed => lang::tr("report-status-resumed"),
+                RowOutcome::Overwritten => lang::tr("report-status-overwritten"),
+                RowOutcome::Unchanged => lang::tr("report-status-unchanged"),
+                RowOutcome::Skipped => lang::tr("report-status-skipped"),
+                RowOutcome::Failed(_) => lang::tr("report-status-failed"),
+            };
+            writer.write_record([
+                row.row.to_string(),
+                status,
+                row.path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+                row.error().unwrap_or_default().to_owned(),
+                match &row.pdf {
+                    Some(Ok(pdf)) => pdf.display().to_string(),
+                    _ => String::new(),
+                },
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Turns the report into result of the whole batch - error listing all the failed rows
+    /// and the recording failure, if any.
+    pub(super) fn into_result(self) -> DocxResult<()> {
+        let rows: Vec<(usize, String)> = self
+            .failed()
+            .map(|r| (r.row, r.error().unwrap_or_default().to_owned()))
+            .collect();
+        match (rows.is_empty(), self.record_failure) {
+            (true, None) => Ok(()),
+            (true, Some(details)) => Err(DocxError::NotRecorded { details }),
+            (false, record_failure) => Err(DocxError::RowsFailed {
+                rows,
+                record_failure,
+            }),
+        }
+    }
+}
+
+/// Returns path of the batch report stored alongside the template (`piagam.docx` -> `piagam.report.csv`).
+pub fn report_path<P: AsRef<Path>>(template: P) -> PathBuf {
+    template.as_ref().with_extension(REPORT_EXTENSION)
+}
@@ -0,0 +1,212 @@
+use super::{DocxError, DocxResult};
+use fs2::FileExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+/// Name of the application data folder.
+const APP_FOLDER: &str = "PiagamDispendik";
+
+/// How long to wait for other process holding the store lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between attempts to acquire the store lock.
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Returns path of a store file in the application data folder of the current user.
+pub fn app_data_path(file: &str) -> PathBuf {
+    let base = env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_default();
+    base.join(APP_FOLDER).join(file)
+}
+
+/// Returns hex encoded SHA-256 digest of the file content.
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> DocxResult<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path.as_ref())?), &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Returns hex encoded SHA-256 digest of the data.
+pub fn sha256(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Loads JSON store from the file, returns default (empty) store if the file does not exist yet.
+pub fn load_json<T: DeserializeOwned + Default, P: AsRef<Path>>(path: P) -> DocxResult<T> {
+    match File::open(path.as_ref()) {
+        Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Stores JSON into the file atomically - the content is written aside, and then renamed over the original,
+/// so the store is never left half written.
+pub fn save_json<T: Serialize, P: AsRef<Path>>(path: P, store: &T) -> DocxResult<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let tmp_path = sibling_path(path, "tmp");
+    let mut tmp = File::create(&tmp_path)?;
+    tmp.write_all(&serde_json::to_vec_pretty(store)?)?;
+    tmp.sync_all()?;
+    drop(tmp);
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Exclusive lock of a store file shared by multiple processes (lock file next to the store).
+/// The lock is held by the operating system on the open lock file - it is released when dropped,
+/// and also when the holding process crashes or is killed, so a left over lock file never blocks the store.
+#[derive(Debug)]
+pub struct StoreLock {
+    file: File,
+}
+
+impl StoreLock {
+    /// Acquires the lock of the store, waiting for other holder up to few seconds.
+    ///
+    /// # Errors
+    ///
+    /// Can return Docx::StoreLocked when the lock is not released in time.
+    pub fn acquire<P: AsRef<Path>>(store: P) -> DocxResult<Self> {
+        let path = sibling_path(store.as_ref(), "lock");
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(Self { file }),
+                Err(err) if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(DocxError::StoreLocked { path });
+                    }
+                    thread::sleep(LOCK_RETRY);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        // the lock file itself is kept, removing it could break the lock of a process waiting for it
+        let _ = self.file.unlock();
+    }
+}
+
+/// Returns path of a helper file next to the store (`nomor.json` -> `nomor.json.lock`).
+pub(super) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Returns current local time (UTC if the local offset cannot be determined).
+pub fn now() -> OffsetDateTime {
+    OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc())
+}
+
+/// Formats the time as it is kept in the stores.
+pub fn timestamp(date: OffsetDateTime) -> String {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+    date.format(&format).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piagamdispendik::testing::TempDir;
+    use std::collections::BTreeMap;
+    use std::sync::mpsc;
+
+    #[test]
+    fn left_over_lock_file_does_not_block() {
+        let dir = TempDir::new("store-stale-lock");
+        let store = dir.join("nomor.json");
+        // lock file of a crashed process
+        fs::write(dir.join("nomor.json.lock"), "").unwrap();
+
+        let started = Instant::now();
+        let lock = StoreLock::acquire(&store).unwrap();
+
+        assert!(started.elapsed() < LOCK_TIMEOUT);
+        drop(lock);
+        StoreLock::acquire(&store).unwrap();
+    }
+
+    #[test]
+    fn lock_waits_for_its_holder() {
+        let dir = TempDir::new("store-lock");
+        let store = dir.join("nomor.json");
+        let lock = StoreLock::acquire(&store).unwrap();
+
+        let (acquired, waiting) = mpsc::channel();
+        let other = {
+            let store = store.clone();
+            thread::spawn(move || {
+                let lock = StoreLock::acquire(&store);
+                acquired.send(()).unwrap();
+                lock.map(|_| ())
+            })
+        };
+        assert!(waiting.recv_timeout(Duration::from_millis(300)).is_err());
+
+        drop(lock);
+        waiting.recv_timeout(LOCK_TIMEOUT).unwrap();
+        other.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn json_store_round_trip() {
+        let dir = TempDir::new("store-json");
+        let path = dir.join("data").join("store.json");
+        let empty: BTreeMap<String, u64> = load_json(&path).unwrap();
+        assert!(empty.is_empty());
+
+        let store: BTreeMap<String, u64> = [("piagam".to_owned(), 7)].into_iter().collect();
+        save_json(&path, &store).unwrap();
+
+        assert_eq!(load_json::<BTreeMap<String, u64>, _>(&path).unwrap(), store);
+        assert!(!sibling_path(&path, "tmp").exists());
+    }
+
+    #[test]
+    fn sha256_of_file_and_data_match() {
+        let dir = TempDir::new("store-sha256");
+        let path = dir.join("piagam.docx");
+        fs::write(&path, "abc").unwrap();
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256(b"abc"), expected);
+        assert_eq!(sha256_file(&path).unwrap(), expected);
+    }
+}
@@ -0,0 +1,188 @@
+//! Helpers of the unit tests - scratch folders and minimal DOCX templates.
+
+use super::convert::{self, Converter};
+use super::DocxResult;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+const CONTENT_TYPES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+    r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+    r#"<Override PartName="/word/document.xml" "#,
+    r#"ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>"#,
+    r#"<Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>"#,
+    r#"</Types>"#
+);
+
+const PACKAGE_RELATIONSHIPS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" "#,
+    r#"Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" "#,
+    r#"Target="word/document.xml"/>"#,
+    r#"<Relationship Id="rId2" "#,
+    r#"Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" "#,
+    r#"Target="docProps/core.xml"/>"#,
+    r#"</Relationships>"#
+);
+
+pub const CORE_PROPERTIES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" "#,
+    r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" "#,
+    r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+    r#"<dc:title>Piagam</dc:title><dc:creator>Dispendik</dc:creator>"#,
+    r#"<dcterms:created xsi:type="dcterms:W3CDTF">2022-01-01T00:00:00Z</dcterms:created>"#,
+    r#"<dcterms:modified xsi:type="dcterms:W3CDTF">2022-01-02T00:00:00Z</dcterms:modified>"#,
+    r#"</cp:coreProperties>"#
+);
+
+/// Scratch folder of a single test, removed when dropped.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "piagamdispendik-{}-{}-{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("scratch folder created");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+
+    /// Returns output pattern of the files in the folder, e.g. `{{nama}}.docx`.
+    pub fn pattern(&self, pattern: &str) -> String {
+        self.join(pattern).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Returns document part with the body content (and landscape section properties).
+pub fn document(body: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            r#"<w:body>{}<w:sectPr><w:pgSz w:w="16838" w:h="11906" w:orient="landscape"/></w:sectPr>"#,
+            r#"</w:body></w:document>"#
+        ),
+        body
+    )
+}
+
+/// Returns paragraph with the text.
+pub fn paragraph(text: &str) -> String {
+    format!("<w:p><w:r><w:t>{}</w:t></w:r></w:p>", text)
+}
+
+/// Writes DOCX template with the body content, and the extra parts.
+pub fn write_template(path: &Path, body: &str, extra: &[(&str, &str)]) {
+    let mut zip = ZipWriter::new(File::create(path).expect("template created"));
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_owned()),
+        ("_rels/.rels", PACKAGE_RELATIONSHIPS.to_owned()),
+        ("docProps/core.xml", CORE_PROPERTIES.to_owned()),
+        ("word/document.xml", document(body)),
+    ];
+    let extra = extra
+        .iter()
+        .map(|(name, content)| (*name, content.to_string()));
+    for (name, content) in parts.into_iter().chain(extra) {
+        zip.start_file(name, FileOptions::default())
+            .expect("part started");
+        zip.write_all(content.as_bytes()).expect("part written");
+    }
+    zip.finish().expect("template finished");
+}
+
+/// Returns content of the part of the DOCX file.
+pub fn read_part(docx: &Path, name: &str) -> String {
+    read_part_bytes(&fs::read(docx).expect("docx read"), name)
+}
+
+/// Returns content of the part of the DOCX package.
+pub fn read_part_bytes(docx: &[u8], name: &str) -> String {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(docx)).expect("docx opened");
+    let mut part = zip.by_name(name).expect("part present");
+    let mut content = String::new();
+    part.read_to_string(&mut content).expect("part read");
+    content
+}
+
+/// Returns names of the parts of the DOCX package, in order of the package.
+pub fn part_names(docx: &[u8]) -> Vec<String> {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(docx)).expect("docx opened");
+    (0..zip.len())
+        .map(|i| zip.by_index(i).expect("part present").name().to_owned())
+        .collect()
+}
+
+pub fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Converter writing placeholder PDFs without converting anything.
+#[derive(Debug, Default)]
+pub struct FakeConverter {
+    /// files the conversion of which fails.
+    pub failing: HashSet<PathBuf>,
+
+    converted: Mutex<Vec<PathBuf>>,
+}
+
+impl FakeConverter {
+    /// Returns the files converted so far, in order of their conversion.
+    pub fn converted(&self) -> Vec<PathBuf> {
+        self.converted
+            .lock()
+            .map(|converted| converted.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Converter for FakeConverter {
+    fn convert(&self, files: &[PathBuf]) -> Vec<DocxResult<PathBuf>> {
+        files
+            .iter()
+            .map(|file| {
+                if self.failing.contains(file) {
+                    return Err(convert::conversion_error(file, "fake failure"));
+                }
+                let pdf = convert::pdf_path(file);
+                fs::write(&pdf, "%PDF-1.4\n%%EOF\n")?;
+                if let Ok(mut converted) = self.converted.lock() {
+                    converted.push(file.clone());
+                }
+                Ok(pdf)
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,9 @@
-use super::{DocxError, TokenPackArg, ValuePack, ValuePackArg};
-use crate::lang;
-use std::collections::HashMap;
+use super::archive::PlannedArchive;
+use super::context::RowContext;
+use super::paths;
+use super::{BatchOptions, DocxError, TokenPackArg, ValuePack, ValuePackArg};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Verifies consistency of input data for a single DOCX generation.
 ///
@@ -12,19 +15,28 @@ use std::collections::HashMap;
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
 pub fn validate_single(
     tokens: TokenPackArg,
     values: ValuePackArg,
     output_pattern: &str,
 ) -> Result<(), DocxError> {
-    validate_tokens(tokens)?;
-    validate_values(tokens, values)?;
-    let filename = super::replace_tokens(output_pattern, tokens, values);
-    validate_filename(&filename)?;
+    validate_render(tokens, values)?;
+    let filename = paths::output_path(None, output_pattern, tokens, values);
+    validate_filename(&filename.to_string_lossy())?;
     Ok(())
 }
 
+/// Verifies consistency of tokens and values a single document is rendered with.
+///
+/// # Errors
+///
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+pub fn validate_render(tokens: TokenPackArg, values: ValuePackArg) -> Result<(), DocxError> {
+    validate_tokens(tokens)?;
+    validate_values(tokens, values)
+}
+
 /// Verifies consistency of input data for a batch of to-be generated documents
 /// (one per each line in input values text).
 ///
@@ -33,18 +45,22 @@ pub fn validate_single(
 /// * `tokens` - vector of tokens to be verified
 /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
 /// * `output_pattern` - output file pattern (pattern containing tokens)
+/// * `options` - batch configuration; tokens covered by constants are not expected in the lines of input text
+/// * `context` - row context the output filenames are previewed with
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
 pub fn validate_batch(
     tokens: TokenPackArg,
     text: &Vec<Vec<String>>,
     output_pattern: &str,
+    options: &BatchOptions,
+    context: &RowContext,
 ) -> Result<(), DocxError> {
     validate_tokens(tokens)?;
-    validate_values_multiline(text, tokens)?;
-    validate_filename_multiline(tokens, text, output_pattern)?;
+    validate_values_multiline(text, &options.constants.row_tokens(tokens))?;
+    validate_filename_multiline(tokens, text, output_pattern, options, context)?;
     Ok(())
 }
 
@@ -56,17 +72,15 @@ pub fn validate_batch(
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
 pub fn validate_tokens(tokens: TokenPackArg) -> Result<(), DocxError> {
     if tokens.is_empty() {
-        return Err(DocxError::Validation(lang::tr("valid-no-tokens")));
+        return Err(DocxError::NoTokens);
     }
     let counts = tokens_counts_map(tokens);
     for (token, count) in counts {
         if count > 1 {
-            let args: lang::TrArgVec = vec![("token".to_string(), token)];
-            let msg = lang::tr_with_args("valid-token-duplicity", &args);
-            return Err(DocxError::Validation(msg));
+            return Err(DocxError::DuplicateToken { token });
         }
     }
     Ok(())
@@ -88,63 +102,173 @@ fn tokens_counts_map(tokens: TokenPackArg) -> HashMap<String, u8> {
 ///
 /// * `tokens` - vector of tokens to be replaced
 /// * `text` - one to many lines of text - a set of values per each line for a new document to be generated
-/// * `separator` - string to be used as a value separator on each line of input
 /// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+/// * `options` - batch configuration - constants and row `context` are filled into the pattern
+///   along with the values of each line
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
 fn validate_filename_multiline(
     tokens: TokenPackArg,
     text: &Vec<ValuePack>,
     output_pattern: &str,
+    options: &BatchOptions,
+    context: &RowContext,
 ) -> Result<(), DocxError> {
-    let mut names: HashMap<String, bool> = Default::default();
-    for values in text {
-        validate_values(tokens, &values)?;
-
-        let filename = super::replace_tokens(output_pattern, tokens, &values);
-        validate_filename(&filename)?;
+    validate_lines(
+        tokens,
+        text,
+        0,
+        output_pattern,
+        options,
+        context,
+        &mut HashSet::new(),
+    )
+}
 
-        if names.contains_key(&filename) {
-            let args: lang::TrArgVec = vec![("filename".to_string(), filename)];
-            let msg = lang::tr_with_args("valid-same-output-filename", &args);
-            return Err(DocxError::Validation(msg));
-        }
-        names.insert(filename, true);
+/// Verifies a part of a streamed batch - the lines starting at the `offset` line of the batch.
+/// Checks the values of each line, and the output filenames against each other and the names
+/// of the previous parts of the batch.
+///
+/// # Arguments
+///
+/// * `tokens` - vector of tokens to be replaced
+/// * `lines` - the lines of values of the part
+/// * `offset` - index of the first line of the part in the batch
+/// * `output_pattern` - output file pattern (explicit string or pattern contains tokens)
+/// * `options` - batch configuration
+/// * `context` - row context of the part
+/// * `names` - output filenames (lowercase) of the batch so far, extended by the names of the part
+///
+/// # Errors
+///
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+pub fn validate_lines(
+    tokens: TokenPackArg,
+    lines: &[ValuePack],
+    offset: usize,
+    output_pattern: &str,
+    options: &BatchOptions,
+    context: &RowContext,
+    names: &mut HashSet<String>,
+) -> Result<(), DocxError> {
+    let row_tokens = options.constants.row_tokens(tokens);
+    for (idx, values) in (offset..).zip(lines.iter()) {
+        validate_line_values(&row_tokens, idx, values)?;
+        validate_line_filename(tokens, idx, values, output_pattern, options, context, names)?;
     }
-
     Ok(())
 }
 
+/// Verifies number of values of the idx-th line of a batch is equal to number of the row tokens.
+///
+/// # Errors
+///
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+pub fn validate_line_values(
+    row_tokens: TokenPackArg,
+    idx: usize,
+    values: ValuePackArg,
+) -> Result<(), DocxError> {
+    validate_values(row_tokens, values).map_err(|err| DocxError::Line {
+        line: idx + 1,
+        source: Box::new(err),
+    })
+}
+
+/// Verifies output filename of the idx-th line of a batch - whether it is valid,
+/// different from the names of the other lines (collected lowercase in `names`, as file names
+/// on Windows ignore the case), and not existing unless the overwrite policy allows it.
+/// Returns the filename.
+///
+/// # Errors
+///
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+pub fn validate_line_filename(
+    tokens: TokenPackArg,
+    idx: usize,
+    values: ValuePackArg,
+    output_pattern: &str,
+    options: &BatchOptions,
+    context: &RowContext,
+    names: &mut HashSet<String>,
+) -> Result<String, DocxError> {
+    let (tokens, values) = options.line_pack(context, idx, tokens, values);
+    let filename = options
+        .output_path(output_pattern, &tokens, &values)
+        .to_string_lossy()
+        .into_owned();
+    validate_filename(&filename)?;
+
+    if !names.insert(filename.to_lowercase()) {
+        return Err(DocxError::SameOutputFile { filename });
+    }
+    if options.overwrite.rejects_existing() && Path::new(&filename).exists() {
+        return Err(DocxError::OutputExists {
+            path: PathBuf::from(filename),
+        });
+    }
+    Ok(filename)
+}
+
 /// Validates the consistency of input sets of tokens and values.
 fn validate_values(tokens: TokenPackArg, values: ValuePackArg) -> Result<(), DocxError> {
     if values.is_empty() {
-        return Err(DocxError::Validation(lang::tr("valid-missing-input")));
+        return Err(DocxError::MissingInput);
     }
 
     if values.len() != tokens.len() {
-        let args: lang::TrArgVec = vec![
-            ("tokens".to_string(), tokens.len().to_string()),
-            ("values".to_string(), values.len().to_string()),
-        ];
-        let msg = lang::tr_with_args("valid-count-mismatch", &args);
-        return Err(DocxError::Validation(msg));
+        return Err(DocxError::CountMismatch {
+            tokens: tokens.len(),
+            values: values.len(),
+        });
     }
 
     Ok(())
 }
 
+/// Verifies the archives planned for a batch - whether the documents have valid names, different
+/// within each archive (ignoring the case, as the names of the extracted files), and the archives
+/// do not exist unless the overwrite policy allows it.
+///
+/// # Errors
+///
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+pub(super) fn validate_archives(
+    archives: &[PlannedArchive],
+    options: &BatchOptions,
+) -> Result<(), DocxError> {
+    for archive in archives {
+        let mut names: HashSet<String> = Default::default();
+        for entry in archive.entries.iter() {
+            validate_filename(&entry.name)?;
+            if !names.insert(entry.name.to_lowercase()) {
+                return Err(DocxError::SameArchiveEntry {
+                    filename: entry.name.clone(),
+                    archive: archive.path.clone(),
+                });
+            }
+        }
+        if options.overwrite.rejects_existing() && archive.path.exists() {
+            return Err(DocxError::OutputExists {
+                path: archive.path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Verifies string to be used as a filename for generated output - whether it has .docx extension.
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
 fn validate_filename(filename: &str) -> Result<(), DocxError> {
     if !filename.ends_with(".docx") {
-        let args: lang::TrArgVec = vec![("filename".to_string(), filename.to_string())];
-        let msg = lang::tr_with_args("valid-no-docx-suffix", &args);
-        return Err(DocxError::Validation(msg));
+        return Err(DocxError::NoDocxSuffix {
+            filename: filename.to_string(),
+        });
     }
     Ok(())
 }
@@ -153,23 +277,18 @@ fn validate_filename(filename: &str) -> Result<(), DocxError> {
 ///
 /// # Errors
 ///
-/// Can return Docx::Validation on failure, with details in message.
-fn validate_values_multiline(
-    text: &Vec<ValuePack>,
-    tokens: TokenPackArg,
-) -> Result<(), DocxError> {
+/// Can return validation error on failure (see [`DocxError`] for the details kept).
+fn validate_values_multiline(text: &Vec<ValuePack>, tokens: TokenPackArg) -> Result<(), DocxError> {
     let mut i: usize = 1;
     if text.is_empty() {
-        return Err(DocxError::Validation(lang::tr("valid-missing-input")));
+        return Err(DocxError::MissingInput);
     }
     for values in text {
         if let Err(err) = validate_values(tokens, &values) {
-            let args: lang::TrArgVec = vec![
-                ("line".to_string(), i.to_string()),
-                ("details".to_string(), err.to_string()),
-            ];
-            let msg = lang::tr_with_args("valid-line-mismatch", &args);
-            return Err(DocxError::Validation(msg));
+            return Err(DocxError::Line {
+                line: i,
+                source: Box::new(err),
+            });
         };
         i += 1;
     }
@@ -144,7 +144,10 @@ pub fn get_parsed_xlsx(
         Err(err) => return Err(format!("XML parsing error: {:?}", err)),
     };
     let known_date_columns: Vec<usize> = date_columns.unwrap_or(Vec::new());
-    let sd = &worksheet.sheet[0];
+    let sd = match worksheet.sheet.first() {
+        Some(sd) => sd,
+        None => return Err("Worksheet has no data".to_owned()),
+    };
     let mut table: HashMap<usize, HashMap<usize, String>> = HashMap::with_capacity(sd.rows.len());
     let mut ir: usize = 0;
     for row in sd.rows.iter() {
@@ -31,3 +31,10 @@ pub const WINDOW_PAD: Rect<Dimension> = Rect {
 };
 
 const W_PT: Dimension = Dimension::Points(0.6 * LINE_HEIGHT);
+
+/// Size (in bytes) of CSV data from which the rows are streamed into the generation,
+/// instead of being loaded (and shown) at once.
+pub const STREAM_DATA_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Count of the duplicate recipients listed in the duplicates report message.
+pub const MAX_REPORTED_DUPLICATES: usize = 15;
@@ -35,7 +35,7 @@ impl DataUi {
         }
         if self.dialog.run(Some(window)) {
             if let Ok(file) = self.dialog.get_selected_item() {
-                return Some(file.into_string().unwrap());
+                return file.into_string().ok();
             }
         }
         None
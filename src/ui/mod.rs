@@ -7,7 +7,18 @@ mod tokens_ui;
 mod values_ui;
 
 use crate::lang;
-use crate::piagamdispendik::{DocxTemplate, DataInput, ValuePack};
+use crate::piagamdispendik::constants::{self, BatchConstants};
+use crate::piagamdispendik::convert::LibreOfficeConverter;
+use crate::piagamdispendik::duplicates::DuplicateCheck;
+use crate::piagamdispendik::manifest;
+use crate::piagamdispendik::merge::MergeOptions;
+use crate::piagamdispendik::numbering::{self, NumberRegistry, Numbering};
+use crate::piagamdispendik::profile::{self, MappingProfile};
+use crate::piagamdispendik::registry::{self, CertificateRegistry, Registration};
+use crate::piagamdispendik::report;
+use crate::piagamdispendik::{
+    BatchOptions, DataInput, DocxError, DocxTemplate, TokenPack, ValuePack,
+};
 use crate::ui::{
     options_ui::OptionsUi, output_ui::OutputUi, template_ui::TemplateUi, tokens_ui::TokensUi,
     values_ui::ValuesUi,
@@ -17,7 +28,9 @@ use nwd::*;
 use nwg::stretch::style::FlexDirection;
 use nwg::NativeUi;
 use std::cell::RefCell;
+use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
 use self::data_ui::DataUi;
 
@@ -38,10 +51,90 @@ pub fn init_app() {
     nwg::dispatch_thread_events();
 }
 
+/// Offline verification of an issued certificate against the local registry -
+/// `--verify <file.docx>` or `--verify <number> <recipient name>`, the result is shown in a message box.
+pub fn verify_certificate(args: &[String]) {
+    nwg::init().unwrap_or_else(|_| panic!("{}", lang::tr("ui-docx-fail-init")));
+
+    let title = lang::tr("ui-verify-title");
+    let registry = CertificateRegistry::new(registry::default_registry_path());
+    let result = match args {
+        [file] => registry.verify_file(file),
+        [number, name @ ..] if !name.is_empty() => registry.verify_number(number, &name.join(" ")),
+        _ => {
+            nwg::error_message(&title, &lang::tr("ui-verify-usage"));
+            return;
+        }
+    };
+    match result {
+        Ok(verification) => nwg::simple_message(&title, &verification.to_message()),
+        Err(err) => nwg::error_message(&title, &err.to_string()),
+    };
+}
+
+/// Revokes the issued certificate given by its number or SHA-256 (`--revoke <id> <reason>`).
+pub fn revoke_certificate(args: &[String]) {
+    nwg::init().unwrap_or_else(|_| panic!("{}", lang::tr("ui-docx-fail-init")));
+
+    let title = lang::tr("ui-registry-title");
+    let registry = CertificateRegistry::new(registry::default_registry_path());
+    let result = match args {
+        [id, reason @ ..] if !reason.is_empty() => registry.revoke(id, &reason.join(" ")),
+        _ => {
+            nwg::error_message(&title, &lang::tr("ui-revoke-usage"));
+            return;
+        }
+    };
+    match result {
+        Ok(revoked) => {
+            let args: lang::TrArgVec = vec![("number".to_string(), revoked.number)];
+            nwg::simple_message(&title, &lang::tr_with_args("ui-revoked", &args))
+        }
+        Err(err) => nwg::error_message(&title, &err.to_string()),
+    };
+}
+
+/// Generates corrected version of the issued certificate (`--reissue <id> {{token}}=value ...`).
+pub fn reissue_certificate(args: &[String]) {
+    nwg::init().unwrap_or_else(|_| panic!("{}", lang::tr("ui-docx-fail-init")));
+
+    let title = lang::tr("ui-registry-title");
+    let registry = CertificateRegistry::new(registry::default_registry_path());
+    let corrections: Option<Vec<(String, String)>> = args
+        .iter()
+        .skip(1)
+        .map(|arg| {
+            arg.split_once('=')
+                .map(|(token, value)| (token.trim().to_owned(), value.to_owned()))
+        })
+        .collect();
+    let result = match (args.first(), corrections) {
+        (Some(id), Some(corrections)) if !corrections.is_empty() => {
+            registry.reissue(id, &corrections)
+        }
+        _ => {
+            nwg::error_message(&title, &lang::tr("ui-reissue-usage"));
+            return;
+        }
+    };
+    match result {
+        Ok(reissued) => {
+            let args: lang::TrArgVec = vec![
+                ("number".to_string(), reissued.number),
+                ("filename".to_string(), reissued.file.display().to_string()),
+            ];
+            nwg::simple_message(&title, &lang::tr_with_args("ui-reissued", &args))
+        }
+        Err(err) => nwg::error_message(&title, &err.to_string()),
+    };
+}
+
 #[derive(NwgUi, Default)]
 pub struct PiagamDispendikApp {
     opened_docx: RefCell<Option<DocxTemplate>>,
     opened_data: RefCell<Option<DataInput>>,
+    active_profile: RefCell<Option<MappingProfile>>,
+    batch_constants: RefCell<BatchConstants>,
 
     #[nwg_control(title: &lang::tr("ui-docx-app-title"), size: (960, 540), position: (80, 60), accept_files: true)]
     #[nwg_events(OnWindowClose: [PiagamDispendikApp::exit(SELF)], OnFileDrop: [PiagamDispendikApp::load_drop_files(SELF, EVT_DATA)])]
@@ -93,7 +186,7 @@ pub struct PiagamDispendikApp {
     #[nwg_layout_item(layout: main_layout, flex_shrink: 1.0, min_size: consts::MIN_TWOLINER_SIZE)]
     output_frame: nwg::Frame,
     #[nwg_partial(parent: output_frame)]
-    #[nwg_events((button, OnButtonClick): [PiagamDispendikApp::generate_docxs(SELF)])]
+    #[nwg_events((button, OnButtonClick): [PiagamDispendikApp::generate_docxs(SELF)], (profile_button, OnButtonClick): [PiagamDispendikApp::save_profile(SELF)])]
     output_partial: OutputUi,
 }
 
@@ -108,22 +201,26 @@ impl PiagamDispendikApp {
 
     fn open_new_file_data(&self) {
         match self.data_partial.get_browse_file(&self.window) {
-            Some(file) => self.load_csv(&file),
+            Some(file) => self.load_data(&file),
             None => eprintln!("file picked from browser file is empty"),
         }
     }
 
     /// Drop of files event handler on app window - loads docx template for processing.
     /// Acts as alternative approach to open file (instead of "Load template" button).
+    /// Constants are loaded only from files named as the constants files (`*.constants.txt`).
     pub fn load_drop_files(&self, data: &nwg::EventData) {
-        for file in data.on_file_drop().files().into_iter() {
+        let mut files = data.on_file_drop().files();
+        // template first, loading the template resets the constants dropped along with it
+        files.sort_by_key(|file| !file.ends_with(".docx"));
+        for file in files.into_iter() {
             // only first file processed - add multiple file handling if/when such feature implemented
             if file.ends_with(".docx") {
                 self.load_docx(&file);
-            } else if file.ends_with(".csv") {
-                self.load_csv(&file);
-            } else if file.ends_with(".xlsx") {
-                self.load_excel(&file);
+            } else if file.ends_with(".csv") || file.ends_with(".xlsx") {
+                self.load_data(&file);
+            } else if file.ends_with(&format!(".{}", constants::CONSTANTS_EXTENSION)) {
+                self.load_constants(&file);
             } else {
                 continue;
             }
@@ -135,26 +232,44 @@ impl PiagamDispendikApp {
         lang::tr("ui-docx-load-failed")
     }
 
-    fn load_csv(&self, file: &str) {
-        match DataInput::open(&file) {
+    /// Loads input data (CSV or Excel workbook) and shows it in the app.
+    /// Only the header of large CSV data is loaded, its rows are streamed from the file during the generation.
+    fn load_data(&self, file: &str) {
+        let large = file.ends_with(".csv")
+            && fs::metadata(file).map_or(false, |m| m.len() > consts::STREAM_DATA_BYTES);
+        let opened = if large {
+            DataInput::open_streamed(file)
+        } else {
+            DataInput::open(file)
+        };
+        match opened {
             Ok(inpt) => {
                 self.data_partial.set_current_data(file);
                 self.values_partial.insert_header(&inpt.header);
-                self.values_partial.load_data(&inpt.file_data);
+                if !inpt.streamed {
+                    self.values_partial.load_data(&inpt.file_data);
+                }
                 self.opened_data.replace(Some(inpt));
+                self.apply_profile();
             }
             Err(error) => {
-                nwg::modal_error_message(
-                    &self.window,
-                    &error.to_string(),
-                    "Error on Opening input data file",
-                );
+                let title = lang::tr("ui-data-load-failed");
+                nwg::modal_error_message(&self.window, &title, &error.to_string());
             }
         }
     }
 
-    fn load_excel(&self, file: &str) {
-        todo!("implements for opening load excel file (xlsx) {:?}", file)
+    /// Loads batch-level constants (one `token = value` per line) shared by all the generated documents.
+    fn load_constants<P: AsRef<Path>>(&self, file: P) {
+        match BatchConstants::load(file.as_ref()) {
+            Ok(constants) => {
+                self.batch_constants.replace(constants);
+            }
+            Err(error) => {
+                let title = lang::tr("ui-docx-failure");
+                nwg::modal_error_message(&self.window, &title, &error.to_string());
+            }
+        }
     }
 
     /// Loads & bind new docx structure from file to the app.
@@ -174,7 +289,7 @@ impl PiagamDispendikApp {
                 self.opened_docx.replace(Some(docx));
 
                 self.template_partial
-                    .set_current_docx(file.as_ref().to_str().unwrap());
+                    .set_current_docx(&file.as_ref().to_string_lossy());
 
                 let separator = self.options_partial.get_separator();
                 self.tokens_partial
@@ -185,6 +300,14 @@ impl PiagamDispendikApp {
 
                 let output_pattern = format!("{}.docx", &tokens[0]);
                 self.output_partial.set_output_pattern(&output_pattern);
+
+                // constants of the previous template do not apply to the new one
+                self.batch_constants.replace(BatchConstants::default());
+                let constants_file = constants::constants_path(file.as_ref());
+                if constants_file.exists() {
+                    self.load_constants(&constants_file);
+                }
+                self.apply_profile();
             }
             Err(err) => {
                 let err_msg = self.failed_load_str();
@@ -206,16 +329,49 @@ impl PiagamDispendikApp {
                 return;
             }
         };
-        let values: Vec<ValuePack> = match self.opened_data.take() {
-            Some(inner) => inner.file_data,
-            None => Vec::new(),
+        let streamed = match &*self.opened_data.borrow() {
+            Some(data) => data.streamed,
+            None => false,
         };
+        // merged and archived batches need all the rows at once
+        let whole_batch = match &*self.active_profile.borrow() {
+            Some(profile) => profile.merge.is_some() || profile.archive_pattern.is_some(),
+            None => false,
+        };
+        if streamed && !whole_batch {
+            self.generate_stream(generator);
+            return;
+        }
+        let (tokens, mut values, mut options) = match self.generation_input(generator) {
+            Ok(input) => input,
+            Err(err) => {
+                let title = lang::tr("ui-docx-failure");
+                nwg::modal_error_message(&self.window, &title, &err.to_string());
+                return;
+            }
+        };
+        if !self.review_duplicates(&tokens, &mut values, &mut options) {
+            return;
+        }
+        let merge = match &*self.active_profile.borrow() {
+            Some(profile) => profile.merge.clone(),
+            None => None,
+        };
+        if let Some(merge) = merge {
+            self.generate_merged(generator, &tokens, &values, &options, &merge);
+            return;
+        }
+        if options.archive_pattern.is_some() {
+            self.generate_archives(generator, &tokens, &values, &options);
+            return;
+        }
 
         let _separator = &self.options_partial.get_separator();
-        match generator.build_docx_batch(
-            &self.tokens_partial.get_selected_tokens(),
+        match generator.build_docx_batch_report(
+            &tokens,
             &values,
             &self.output_partial.output_pattern(),
+            &options,
         ) {
             Err(err) => {
                 nwg::modal_error_message(
@@ -224,7 +380,32 @@ impl PiagamDispendikApp {
                     &err.to_string(),
                 );
             }
-            Ok(()) => {
+            Ok(batch_report) if batch_report.has_failures() => {
+                let path = report::report_path(&generator.input_path);
+                let mut content = match batch_report.save_csv(&path) {
+                    Ok(()) => {
+                        let args: lang::TrArgVec = vec![
+                            (
+                                "generated".to_string(),
+                                batch_report.generated().count().to_string(),
+                            ),
+                            (
+                                "failed".to_string(),
+                                batch_report.failed().count().to_string(),
+                            ),
+                            ("filename".to_string(), path.display().to_string()),
+                        ];
+                        lang::tr_with_args("ui-docx-generated-with-failures", &args)
+                    }
+                    Err(err) => err.to_string(),
+                };
+                if let Some(details) = batch_report.record_failure.clone() {
+                    content.push('\n');
+                    content.push_str(&DocxError::NotRecorded { details }.to_string());
+                }
+                nwg::modal_error_message(&self.window, &lang::tr("ui-docx-failure"), &content);
+            }
+            Ok(_) => {
                 nwg::modal_info_message(
                     &self.window,
                     &lang::tr("ui-docx-success"),
@@ -234,6 +415,336 @@ impl PiagamDispendikApp {
         }
     }
 
+    /// Generates the batch from the rows streamed from large CSV data (see [`DocxTemplate::build_docx_stream`]),
+    /// duplicate recipients are not reviewed as the rows are never loaded all at once.
+    fn generate_stream(&self, generator: &DocxTemplate) {
+        match self.stream_rows(generator) {
+            Ok(rows) => {
+                let args: lang::TrArgVec = vec![("rows".to_string(), rows.to_string())];
+                nwg::modal_info_message(
+                    &self.window,
+                    &lang::tr("ui-docx-success"),
+                    &lang::tr_with_args("ui-docx-streamed", &args),
+                );
+            }
+            Err(err) => {
+                nwg::modal_error_message(
+                    &self.window,
+                    &lang::tr("ui-docx-failure"),
+                    &err.to_string(),
+                );
+            }
+        }
+    }
+
+    /// Streams rows of the opened data into the generation, projected by the active mapping profile
+    /// if there is one. Returns count of the rows generated.
+    fn stream_rows(&self, generator: &DocxTemplate) -> Result<usize, DocxError> {
+        let data_ref = self.opened_data.borrow();
+        let profile_ref = self.active_profile.borrow();
+        let data = data_ref.as_ref().ok_or(DocxError::MissingInput)?;
+        let options = self.batch_options(generator, profile_ref.as_ref());
+        let output_pattern = self.output_partial.output_pattern();
+        let (_, rows) = DataInput::stream_csv(&data.input_path)?;
+        match (&*profile_ref, &data.header) {
+            (Some(profile), Some(header)) => {
+                let (tokens, rows) =
+                    profile.apply_stream(&generator.template_tokens()?, header, rows)?;
+                generator.build_docx_stream(&tokens, rows, &output_pattern, &options)
+            }
+            _ => {
+                let tokens = self.tokens_partial.get_selected_tokens();
+                generator.build_docx_stream(&tokens, rows, &output_pattern, &options)
+            }
+        }
+    }
+
+    /// Generates the batch merged into single document (or one per group) for printing.
+    fn generate_merged(
+        &self,
+        generator: &DocxTemplate,
+        tokens: &TokenPack,
+        values: &[ValuePack],
+        options: &BatchOptions,
+        merge: &MergeOptions,
+    ) {
+        let output_pattern = self.output_partial.output_pattern();
+        match generator.build_docx_merged(tokens, values, &output_pattern, options, merge) {
+            Ok(merged) => {
+                let args: lang::TrArgVec = vec![
+                    ("rows".to_string(), values.len().to_string()),
+                    ("files".to_string(), merged.len().to_string()),
+                ];
+                nwg::modal_info_message(
+                    &self.window,
+                    &lang::tr("ui-docx-success"),
+                    &lang::tr_with_args("ui-docx-merged", &args),
+                );
+            }
+            Err(err) => {
+                nwg::modal_error_message(
+                    &self.window,
+                    &lang::tr("ui-docx-load-failed"),
+                    &err.to_string(),
+                );
+            }
+        }
+    }
+
+    /// Generates the batch written into ZIP archives.
+    fn generate_archives(
+        &self,
+        generator: &DocxTemplate,
+        tokens: &TokenPack,
+        values: &[ValuePack],
+        options: &BatchOptions,
+    ) {
+        let output_pattern = self.output_partial.output_pattern();
+        match generator.build_docx_archives(tokens, values, &output_pattern, options) {
+            Ok(archives) => {
+                let args: lang::TrArgVec = vec![
+                    ("rows".to_string(), values.len().to_string()),
+                    ("files".to_string(), archives.len().to_string()),
+                ];
+                nwg::modal_info_message(
+                    &self.window,
+                    &lang::tr("ui-docx-success"),
+                    &lang::tr_with_args("ui-docx-archived", &args),
+                );
+            }
+            Err(err) => {
+                nwg::modal_error_message(
+                    &self.window,
+                    &lang::tr("ui-docx-load-failed"),
+                    &err.to_string(),
+                );
+            }
+        }
+    }
+
+    /// Reports duplicate recipients of the batch, and lets the user leave them out.
+    /// Returns false when the user decides not to generate the batch at all.
+    fn review_duplicates(
+        &self,
+        tokens: &TokenPack,
+        values: &mut Vec<ValuePack>,
+        options: &mut BatchOptions,
+    ) -> bool {
+        let check = match &*self.active_profile.borrow() {
+            Some(profile) => profile.duplicates.clone(),
+            None => DuplicateCheck::default(),
+        };
+        let report = match check.check(tokens, values, options) {
+            Ok(report) if report.is_empty() => return true,
+            Ok(report) => report,
+            Err(err) => {
+                let title = lang::tr("ui-docx-failure");
+                nwg::modal_error_message(&self.window, &title, &err.to_string());
+                return false;
+            }
+        };
+
+        let duplicates: Vec<String> = report
+            .duplicates
+            .iter()
+            .take(consts::MAX_REPORTED_DUPLICATES)
+            .map(|duplicate| duplicate.to_message(&options.source_rows))
+            .collect();
+        let args: lang::TrArgVec = vec![("duplicates".to_string(), duplicates.join("\n"))];
+        let params = nwg::MessageParams {
+            title: &lang::tr("ui-duplicates-title"),
+            content: &lang::tr_with_args("ui-duplicates-question", &args),
+            buttons: nwg::MessageButtons::YesNoCancel,
+            icons: nwg::MessageIcons::Warning,
+        };
+        match nwg::modal_message(&self.window, &params) {
+            nwg::MessageChoice::Yes => {
+                *values = report.exclude(values);
+                if !options.source_rows.is_empty() {
+                    options.source_rows = report.exclude(&options.source_rows);
+                }
+                true
+            }
+            nwg::MessageChoice::No => true,
+            _ => false,
+        }
+    }
+
+    /// Returns tokens, values and batch options to be generated with - values are projected by the active
+    /// mapping profile if there is one, otherwise the selected tokens are filled with data columns in order.
+    fn generation_input(
+        &self,
+        generator: &DocxTemplate,
+    ) -> Result<(TokenPack, Vec<ValuePack>, BatchOptions), DocxError> {
+        let data_ref = self.opened_data.borrow();
+        let profile_ref = self.active_profile.borrow();
+        let mut options = self.batch_options(generator, profile_ref.as_ref());
+        // streamed data is loaded whole for the batches which need all the rows at once
+        let loaded = match &*data_ref {
+            Some(data) if data.streamed => Some(DataInput::open(&data.input_path)?),
+            _ => None,
+        };
+        let data = loaded.as_ref().or(data_ref.as_ref());
+        let header = data.and_then(|data| data.header.as_ref());
+        match (&*profile_ref, data, header) {
+            (Some(profile), Some(data), Some(header)) => {
+                let file_data = &data.file_data;
+                let (tokens, values) =
+                    profile.apply(&generator.template_tokens()?, header, file_data)?;
+                options.source_rows = profile
+                    .accepted_rows(header, file_data)
+                    .into_iter()
+                    .map(|idx| data.source_row(idx))
+                    .collect();
+                Ok((tokens, values, options))
+            }
+            (_, Some(data), _) => {
+                options.source_rows = (0..data.file_data.len())
+                    .map(|idx| data.source_row(idx))
+                    .collect();
+                Ok((
+                    self.tokens_partial.get_selected_tokens(),
+                    data.file_data.clone(),
+                    options,
+                ))
+            }
+            (_, None, _) => {
+                let tokens = self.tokens_partial.get_selected_tokens();
+                Ok((tokens, Vec::new(), options))
+            }
+        }
+    }
+
+    /// Returns batch options of the generation - the loaded constants, registration into the default
+    /// registry and manifest alongside the template, configured further by the mapping profile if there is one.
+    fn batch_options(
+        &self,
+        generator: &DocxTemplate,
+        profile: Option<&MappingProfile>,
+    ) -> BatchOptions {
+        let mut options = BatchOptions {
+            constants: self.batch_constants.borrow().clone(),
+            registration: Some(Registration {
+                registry: CertificateRegistry::new(registry::default_registry_path()),
+                key_tokens: Vec::new(),
+            }),
+            manifest: Some(manifest::manifest_path(&generator.input_path)),
+            output_dir: generator.input_path.parent().map(Path::to_path_buf),
+            ..Default::default()
+        };
+        let profile = match profile {
+            Some(profile) => profile,
+            None => return options,
+        };
+        options.constants.extend(&profile.batch_constants());
+        options.overwrite = profile.overwrite;
+        options.archive_pattern = profile.archive_pattern.clone();
+        // merged and archived batches cannot be resumed
+        if profile.merge.is_some() || profile.archive_pattern.is_some() {
+            options.manifest = None;
+        }
+        if profile.convert_pdf {
+            options.converter = Some(Arc::new(LibreOfficeConverter::default()));
+        }
+        if let Some(output_dir) = &profile.output_dir {
+            options.output_dir = Some(output_dir.clone());
+        }
+        if let Some(registration) = options.registration.as_mut() {
+            registration.key_tokens = profile.recipient_tokens.clone();
+        }
+        options.numbering = profile.numbering.clone().map(|format| Numbering {
+            registry: NumberRegistry::new(numbering::default_registry_path()),
+            format,
+        });
+        options
+    }
+
+    /// Looks up mapping profile stored alongside the opened template and applies it,
+    /// once both the template and the data with matching header are loaded.
+    fn apply_profile(&self) {
+        self.active_profile.replace(None);
+        if let Some(docx) = self.opened_docx.borrow_mut().as_mut() {
+            docx.deterministic = false;
+            docx.properties = Default::default();
+        }
+        let docx_ref = self.opened_docx.borrow();
+        let data_ref = self.opened_data.borrow();
+        let header = data_ref.as_ref().and_then(|data| data.header.as_ref());
+        let (docx, header) = match (&*docx_ref, header) {
+            (Some(docx), Some(header)) => (docx, header),
+            _ => return,
+        };
+
+        let profile = match MappingProfile::load_for_template(&docx.input_path) {
+            Ok(Some(profile)) if profile.matches_header(header) => profile,
+            Ok(_) => return,
+            Err(err) => {
+                let title = lang::tr("ui-docx-failure");
+                nwg::modal_error_message(&self.window, &title, &err.to_string());
+                return;
+            }
+        };
+
+        if !profile.output_pattern.is_empty() {
+            self.output_partial
+                .set_output_pattern(&profile.output_pattern);
+        }
+        if let Ok(tokens) = docx.template_tokens() {
+            let warnings: Vec<String> = profile
+                .check_tokens(&tokens)
+                .iter()
+                .map(|w| w.to_message())
+                .collect();
+            if !warnings.is_empty() {
+                let title = lang::tr("ui-profile-warnings");
+                nwg::modal_info_message(&self.window, &title, &warnings.join("\n"));
+            }
+        }
+        drop(docx_ref);
+        if let Some(docx) = self.opened_docx.borrow_mut().as_mut() {
+            docx.deterministic = profile.deterministic;
+            docx.properties = profile.properties.clone();
+        }
+        self.active_profile.replace(Some(profile));
+    }
+
+    /// Stores current token selection (n-th token filled from n-th data column) as a mapping profile
+    /// alongside the opened template.
+    fn save_profile(&self) {
+        let docx_ref = self.opened_docx.borrow();
+        let data_ref = self.opened_data.borrow();
+        let header = data_ref.as_ref().and_then(|data| data.header.as_ref());
+        let (docx, header) = match (&*docx_ref, header) {
+            (Some(docx), Some(header)) => (docx, header),
+            _ => {
+                let title = lang::tr("ui-docx-failure");
+                let content = lang::tr("ui-docx-no-template");
+                nwg::modal_info_message(&self.window, &title, &content);
+                return;
+            }
+        };
+
+        let profile = MappingProfile::from_selection(
+            &self.tokens_partial.get_selected_tokens(),
+            header,
+            &self.output_partial.output_pattern(),
+        );
+        match profile.save(profile::profile_path(&docx.input_path)) {
+            Ok(()) => {
+                nwg::modal_info_message(
+                    &self.window,
+                    &lang::tr("ui-docx-success"),
+                    &lang::tr("ui-profile-saved"),
+                );
+                self.active_profile.replace(Some(profile));
+            }
+            Err(err) => {
+                let title = lang::tr("ui-docx-failure");
+                nwg::modal_error_message(&self.window, &title, &err.to_string());
+            }
+        }
+    }
+
     /// Invoke language change from the "options" partial...
     fn set_lang(&self) {
         if let Err(msg) = self.options_partial.set_current_lang() {
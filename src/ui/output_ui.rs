@@ -20,6 +20,10 @@ pub struct OutputUi {
     #[nwg_control(text: &tr("ui-output-button"))]
     #[nwg_layout_item(layout: layout, size: Size { width: Dimension::Points(180.0), height: consts::BUTTON_HEIGHT })]
     pub button: nwg::Button,
+
+    #[nwg_control(text: &tr("ui-output-profile-button"))]
+    #[nwg_layout_item(layout: layout, size: Size { width: Dimension::Points(180.0), height: consts::BUTTON_HEIGHT })]
+    pub profile_button: nwg::Button,
 }
 
 impl OutputUi {
@@ -34,5 +38,7 @@ impl OutputUi {
     pub fn reset_language(&self) {
         self.label.set_text(&tr("ui-output-label"));
         self.button.set_text(&tr("ui-output-button"));
+        self.profile_button
+            .set_text(&tr("ui-output-profile-button"));
     }
 }